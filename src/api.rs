@@ -1,6 +1,11 @@
 use crate::prelude::*;
 use openapiv3::OpenAPI;
-use std::{fs::File, io::Read, path::Path};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::File,
+    io::Read,
+    path::Path,
+};
 
 pub struct Api {
     schema: OpenAPI,
@@ -57,49 +62,439 @@ impl Api {
         }
         for (name, schema) in self.schema.components.as_ref().unwrap().schemas.iter() {
             let schema = schema.as_item().unwrap();
-            Model::discover(name, schema)?;
+            Model::discover(&Model::type_name(name), schema)?;
+        }
+        for (name, schema) in self.schema.components.as_ref().unwrap().responses.iter() {
+            Response::discover_component(name, schema.as_item().unwrap())?;
+        }
+        for (name, schema) in self
+            .schema
+            .components
+            .as_ref()
+            .unwrap()
+            .request_bodies
+            .iter()
+        {
+            Content::add_request_body(name, schema.as_item().unwrap());
+        }
+        for (name, schema) in self
+            .schema
+            .components
+            .as_ref()
+            .unwrap()
+            .security_schemes
+            .iter()
+        {
+            Security::discover(name, schema.as_item().unwrap())?;
+        }
+        if let Some(requirements) = self.schema.security.as_ref() {
+            Security::set_default(requirements)?;
+        }
+        for server in self.schema.servers.iter() {
+            Server::discover(server)?;
+        }
+        for tag in self.schema.tags.iter() {
+            Tag::discover(&tag.name, tag.description.clone());
         }
         for (path, schema) in self.schema.paths.iter() {
             if path.starts_with("/v1/environments") {
                 Operation::discover_all_from_path(path, schema)?;
             }
         }
+        if Config::get().mock {
+            for operation in Operation::all() {
+                Mock::discover(&operation, &self.schema)?;
+            }
+        }
         self.write()?;
         Ok(())
     }
 
+    fn source(&self) -> String {
+        match Config::get().package {
+            Some(_) => format!("{}/src", self.output),
+            None => self.output.clone(),
+        }
+    }
+
+    fn write_modules(&self) -> Result<BTreeSet<String>, Error> {
+        let source = self.source();
+        let layout = Config::get().layout;
+        let components = self
+            .schema
+            .components
+            .as_ref()
+            .unwrap()
+            .schemas
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        let placement = layout.modules(&components)?;
+        let mut clients = BTreeMap::new();
+        for tag in Tag::all() {
+            clients.insert(tag.module(), tag.tokens()?);
+        }
+        let mut grouped: BTreeMap<String, (Tokens, BTreeSet<String>)> = BTreeMap::new();
+        let mut models = Tokens::new();
+        let mut names = BTreeSet::new();
+        for model in Model::all() {
+            if layout == Layout::Models {
+                write_tokens(
+                    &format!("{}/model/{}.rs", source, model.module()),
+                    Api::scoped(model.tokens()?, &BTreeSet::from([model.name.clone()]))?,
+                )?;
+                quote_in!(models =>
+                    mod $(model.module());
+                    pub use $(model.module())::*;
+                );
+                continue;
+            }
+            let (tokens, names) = match placement.get(&model.name) {
+                Some(module) => {
+                    let (tokens, names) = grouped.entry(module.clone()).or_default();
+                    (tokens, names)
+                }
+                None => (&mut models, &mut names),
+            };
+            tokens.append(model.tokens()?);
+            tokens.line();
+            names.insert(model.name.clone());
+        }
+        write_tokens(
+            &format!(
+                "{}/{}",
+                source,
+                if layout == Layout::Models {
+                    "model/mod.rs"
+                } else {
+                    "model.rs"
+                }
+            ),
+            match layout {
+                Layout::Models => models,
+                _ => Api::scoped(models, &names)?,
+            },
+        )?;
+        let modules = clients
+            .keys()
+            .chain(grouped.keys())
+            .cloned()
+            .collect::<BTreeSet<_>>();
+        for module in modules.iter() {
+            match (clients.remove(module), grouped.remove(module)) {
+                (Some(client), Some((models, names))) => {
+                    write_tokens(
+                        &format!("{source}/{module}/mod.rs"),
+                        quote!(
+                            use super::*;
+                            $['\n']
+                            mod client;
+                            mod model;
+                            $['\n']
+                            pub use client::*;
+                            pub use model::*;
+                        ),
+                    )?;
+                    write_tokens(&format!("{source}/{module}/client.rs"), client)?;
+                    write_tokens(
+                        &format!("{source}/{module}/model.rs"),
+                        Api::scoped(models, &names)?,
+                    )?;
+                }
+                (Some(client), None) => {
+                    write_tokens(&format!("{source}/{module}.rs"), client)?;
+                }
+                (None, Some((models, names))) => {
+                    write_tokens(
+                        &format!("{source}/{module}.rs"),
+                        Api::scoped(models, &names)?,
+                    )?;
+                }
+                (None, None) => {}
+            }
+        }
+        Ok(modules)
+    }
+
+    fn scoped(models: Tokens, names: &BTreeSet<String>) -> Result<Tokens, Error> {
+        let external = identifiers(&models)?
+            .into_iter()
+            .any(|name| !names.contains(&name) && Model::get(&name).is_some());
+        if !external {
+            return Ok(models);
+        }
+        Ok(quote!(
+            use super::*;
+            $['\n']
+            $models
+        ))
+    }
+
+    fn blocking_tokens(&self) -> Tokens {
+        quote!(
+            use super::*;
+            use std::{future::Future, sync::Arc};
+            use tokio::runtime::Runtime;
+            $['\n']
+            #[doc = "Runs the async client on its own runtime, so it must not be used from async code"]
+            pub struct Api {
+                inner: super::Api,
+                runtime: Arc<Runtime>,
+            }
+            $['\n']
+            impl From<super::Api> for Api {
+                fn from(inner: super::Api) -> Self {
+                    let runtime = tokio::runtime::Builder::new_current_thread()
+                        .enable_all()
+                        .build()
+                        .expect("could not start a tokio runtime");
+                    Self {
+                        inner,
+                        runtime: Arc::new(runtime),
+                    }
+                }
+            }
+            $['\n']
+            impl Api {
+                pub fn new<P: CredentialProvider + 'static>(provider: P) -> Self {
+                    Self::from(super::Api::new(provider))
+                }
+                $['\n']
+                #[doc = "Drives a future, such as the next item of a streamed response, to completion"]
+                pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+                    self.runtime.block_on(future)
+                }
+                $['\n']
+                $(for operation in Operation::all().iter().filter(|operation| operation.tag.is_none()) =>
+                    $(operation.blocking_tokens(&operation.name, quote!(self), Tokens::new()))
+                    $['\n']
+                )
+            }
+            $['\n']
+            $(for tag in Tag::all() =>
+                $(tag.blocking_tokens())
+                $['\n']
+            )
+        )
+    }
+
     pub fn write(&self) -> Result<(), Error> {
+        if Config::get().package.is_some() {
+            Model::gate()?;
+        }
+        let package = Config::get().package.clone().unwrap_or_default();
+        let user_agent = format!(
+            "{}/{}",
+            package.name(&self.schema),
+            package.version(&self.schema)
+        );
+        let (enforce_http, tls) = match Config::get().package {
+            Some(_) => (
+                quote!(http.enforce_http(!cfg!(any(feature = "native-tls", feature = "rustls")));),
+                quote!(
+                    #[cfg(feature = "rustls")]
+                    let connector = hyper_rustls::HttpsConnectorBuilder::new()
+                        .with_native_roots()
+                        .https_or_http()
+                        .enable_http1()
+                        .enable_http2()
+                        .wrap_connector(connector);
+                    #[cfg(all(feature = "native-tls", not(feature = "rustls")))]
+                    let connector = hyper_tls::HttpsConnector::new_with_connector(connector);
+                ),
+            ),
+            None => (
+                quote!(http.enforce_http(false);),
+                quote!(let connector = hyper_tls::HttpsConnector::new_with_connector(connector);),
+            ),
+        };
+        let mut errors = BTreeSet::new();
+        for operation in Operation::all() {
+            for response in Response::errors(&operation.responses) {
+                if let Body::Json(ty) = response.body {
+                    errors.insert(ty);
+                }
+            }
+        }
+        let source = self.source();
+        let root = match &Config::get().package {
+            Some(package) => {
+                package.write(&self.schema, &self.output)?;
+                "lib.rs"
+            }
+            None => "mod.rs",
+        };
+        let modules = self.write_modules()?;
+        if Config::get().blocking {
+            write_qualified_tokens(&format!("{source}/blocking.rs"), self.blocking_tokens())?;
+        }
+        if Config::get().mock {
+            write_qualified_tokens(&format!("{source}/mock.rs"), Mock::tokens())?;
+        }
+        if Config::get().service {
+            write_qualified_tokens(&format!("{source}/service.rs"), Service::tokens())?;
+        }
+        let features = Tag::all()
+            .iter()
+            .filter_map(|tag| Some((tag.module(), tag.feature()?)))
+            .collect::<BTreeMap<_, _>>();
+        let module_cfg = |module: &String| match features.get(module) {
+            Some(feature) => cfg(&BTreeSet::from([feature.clone()])),
+            None => Tokens::new(),
+        };
+        let model_cfg = |ty: &String| match Model::get(ty) {
+            Some(model) => cfg(&model.features),
+            None => Tokens::new(),
+        };
         write_tokens(
-            &format!("{}/mod.rs", self.output),
+            &format!("{source}/{root}"),
             quote!(
                 pub mod api;
+                pub mod error;
                 pub mod model;
+                $(for module in &modules =>
+                    $(module_cfg(module))
+                    pub mod $module;
+                )
+                $(if Config::get().blocking {
+                    pub mod blocking;
+                })
+                $(if Config::get().mock {
+                    $(if Config::get().package.is_some() {
+                        #[cfg(feature = "mock")]
+                    })
+                    pub mod mock;
+                })
+                $(if Config::get().service {
+                    $(if Config::get().package.is_some() {
+                        #[cfg(feature = "service")]
+                    })
+                    pub mod service;
+                })
                 $['\n']
-                pub use api::Api;
+                pub use api::*;
+                pub use error::Error;
                 pub use model::*;
-                $['\n']
-                pub type Error = Box<dyn std::error::Error + Send + Sync>;
+                $(for module in &modules =>
+                    $(module_cfg(module))
+                    pub use $module::*;
+                )
             ),
         )?;
         write_tokens(
-            &format!("{}/model.rs", self.output),
+            &format!("{source}/error.rs"),
             quote!(
-                $(for model in Model::all() =>
-                    $(model.tokens()?)
+                use super::ApiError;
+                use hyper::{body::Bytes, StatusCode};
+                $['\n']
+                #[derive(Debug)]
+                pub enum Error {
+                    Transport(hyper::Error),
+                    #[doc = "Returned by custom `HttpTransport`s when a request could not reach the server, retried like `Transport` connect errors"]
+                    Connection(Box<dyn std::error::Error + Send + Sync>),
+                    Timeout,
+                    Serialize(Box<dyn std::error::Error + Send + Sync>),
+                    Deserialize {
+                        body: Bytes,
+                        path: String,
+                        source: serde_json::Error,
+                    },
+                    Status {
+                        code: StatusCode,
+                        body: Bytes,
+                    },
+                    Api(ApiError),
+                    Validation(String),
+                }
+                $['\n']
+                impl Error {
+                    pub fn status(&self) -> Option<StatusCode> {
+                        match self {
+                            Error::Status { code, .. } => Some(*code),
+                            Error::Api(error) => Some(error.status()),
+                            _ => None,
+                        }
+                    }
                     $['\n']
-                )
+                    pub fn is_retryable(&self) -> bool {
+                        match self {
+                            Error::Transport(error) => error.is_connect() || error.is_incomplete_message(),
+                            Error::Connection(_) | Error::Timeout => true,
+                            _ => matches!(
+                                self.status().map(|status| status.as_u16()),
+                                Some(408 | 429 | 500 | 502 | 503 | 504)
+                            ),
+                        }
+                    }
+                }
+                $['\n']
+                impl std::fmt::Display for Error {
+                    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                        match self {
+                            Error::Transport(error) => write!(f, "transport error: {error}"),
+                            Error::Connection(error) => write!(f, "connection error: {error}"),
+                            Error::Timeout => write!(f, "request timed out"),
+                            Error::Serialize(error) => write!(f, "could not serialize request: {error}"),
+                            Error::Deserialize { path, source, .. } => {
+                                write!(f, "could not deserialize response at {path}: {source}")
+                            }
+                            Error::Status { code, body } => {
+                                write!(f, "unexpected status {code}: {}", String::from_utf8_lossy(body))
+                            }
+                            Error::Api(error) => write!(f, "api error {error}"),
+                            Error::Validation(message) => write!(f, "invalid request: {message}"),
+                        }
+                    }
+                }
+                $['\n']
+                impl std::error::Error for Error {
+                    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+                        match self {
+                            Error::Transport(error) => Some(error),
+                            Error::Connection(error) | Error::Serialize(error) => Some(error.as_ref()),
+                            Error::Deserialize { source, .. } => Some(source),
+                            Error::Api(error) => Some(error),
+                            _ => None,
+                        }
+                    }
+                }
+                $['\n']
+                impl From<hyper::Error> for Error {
+                    fn from(error: hyper::Error) -> Self {
+                        Error::Transport(error)
+                    }
+                }
+                $['\n']
+                impl From<ApiError> for Error {
+                    fn from(error: ApiError) -> Self {
+                        Error::Api(error)
+                    }
+                }
+                $['\n']
+                impl From<serde_urlencoded::ser::Error> for Error {
+                    fn from(error: serde_urlencoded::ser::Error) -> Self {
+                        Error::Serialize(Box::new(error))
+                    }
+                }
             ),
         )?;
-        write_tokens(
-            &format!("{}/api.rs", self.output),
+        write_qualified_tokens(
+            &format!("{source}/api.rs"),
             quote!(
                 use super::Error;
-                use hyper::{Client, Uri, client::HttpConnector, Method};
-                use hyper_tls::HttpsConnector;
+                use hyper::{
+                    Client, Uri, client::{connect::Connect, HttpConnector}, Method, HeaderMap, StatusCode, body::Bytes,
+                    header::{HeaderName, HeaderValue}, service::Service,
+                };
+                use futures_util::{future::{self, BoxFuture}, stream::{self, BoxStream}, FutureExt, StreamExt};
                 use serde_json::Value;
-                use serde::{de::DeserializeOwned, Serialize};
+                use serde::{de::DeserializeOwned, Deserialize, Serialize};
+                use std::{
+                    collections::{hash_map::RandomState, BTreeMap}, future::Future, hash::{BuildHasher, Hasher}, sync::{Arc, Mutex}, task::{Context, Poll}, time::{Duration, Instant},
+                };
+                use tokio::{io::{AsyncReadExt, AsyncWriteExt}, net::TcpStream};
                 $['\n']
-                pub fn resolve<S: Serialize>(object: &mut Value, name: &str, value: Option<S>) {
+                pub(crate) fn resolve<S: Serialize>(object: &mut Value, name: &str, value: Option<S>) {
                     if let Some(value) = value {
                         let value = serde_json::to_value(value).unwrap();
                         object[name] = if let serde_json::Value::Array(value) = value {
@@ -116,61 +511,921 @@ impl Api {
                     }
                 }
                 $['\n']
+                pub(crate) fn header<T: std::str::FromStr>(headers: &HeaderMap, name: &str) -> Result<Option<T>, Error> {
+                    match headers.get(name) {
+                        Some(value) => value
+                            .to_str()
+                            .ok()
+                            .and_then(|value| value.parse().ok())
+                            .map(Some)
+                            .ok_or_else(|| Error::Validation(format!("invalid {name} header"))),
+                        None => Ok(None),
+                    }
+                }
+                $['\n']
+                pub(crate) fn with_query(path: &str, pairs: &[(&str, String)]) -> Result<String, Error> {
+                    let (base, query) = path.split_once('?').unwrap_or((path, ""));
+                    let mut query = serde_urlencoded::from_str::<Vec<(String, String)>>(query)
+                        .map_err(|error| Error::Serialize(Box::new(error)))?;
+                    query.retain(|(key, _)| !pairs.iter().any(|(name, _)| key == name));
+                    query.extend(pairs.iter().map(|(name, value)| (name.to_string(), value.clone())));
+                    Ok(format!("{base}?{}", serde_urlencoded::to_string(&query)?))
+                }
+                $['\n']
+                pub(crate) fn encode_path(segment: &str) -> String {
+                    let mut encoded = String::with_capacity(segment.len());
+                    for byte in segment.bytes() {
+                        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+                            encoded.push(byte as char);
+                        } else {
+                            encoded.push_str(&format!("%{byte:02X}"));
+                        }
+                    }
+                    encoded
+                }
+                $['\n']
+                pub(crate) fn decode<T: DeserializeOwned>(body: &Bytes) -> Result<T, Error> {
+                    let deserializer = &mut serde_json::Deserializer::from_slice(body);
+                    serde_path_to_error::deserialize(deserializer).map_err(|error| Error::Deserialize {
+                        body: body.clone(),
+                        path: error.path().to_string(),
+                        source: error.into_inner(),
+                    })
+                }
+                $['\n']
+                pub(crate) async fn bytes(response: hyper::Response<hyper::Body>) -> Result<Bytes, Error> {
+                    let log = response.extensions().get::<LogBody>().is_some();
+                    let body = hyper::body::to_bytes(response.into_body()).await?;
+                    if log {
+                        tracing::debug!(body = %String::from_utf8_lossy(&body), "response body");
+                    }
+                    Ok(body)
+                }
+                $['\n']
+                #[derive(Clone, Copy)]
+                struct LogBody;
+                $['\n']
+                const REDACTED: &[&str] = &[
+                    "authorization",
+                    "proxy-authorization",
+                    "cookie",
+                    "set-cookie",
+                    $(for name in Security::redacted() => $(quoted(name)),)
+                ];
+                $['\n']
+                fn redact_headers(headers: &HeaderMap) -> Vec<(String, String)> {
+                    headers
+                        .iter()
+                        .map(|(name, value)| {
+                            let value = if REDACTED.contains(&name.as_str()) {
+                                "[redacted]".to_string()
+                            } else {
+                                String::from_utf8_lossy(value.as_bytes()).into_owned()
+                            };
+                            (name.to_string(), value)
+                        })
+                        .collect()
+                }
+                $['\n']
+                fn redact_uri(uri: &Uri) -> String {
+                    let uri = uri.to_string();
+                    let (base, query) = match uri.split_once('?') {
+                        Some(parts) => parts,
+                        None => return uri,
+                    };
+                    let query = query
+                        .split('&')
+                        .map(|pair| match pair.split_once('=') {
+                            Some((key, _)) if REDACTED.contains(&key) => format!("{key}=[redacted]"),
+                            _ => pair.to_string(),
+                        })
+                        .collect::<Vec<_>>()
+                        .join("&");
+                    format!("{base}?{query}")
+                }
+                $['\n']
+                pub(crate) async fn text(response: hyper::Response<hyper::Body>) -> Result<String, Error> {
+                    Ok(String::from_utf8_lossy(&bytes(response).await?).into_owned())
+                }
+                $['\n']
+                pub struct EventStream<T> {
+                    inner: BoxStream<'static, Result<T, Error>>,
+                }
+                $['\n']
+                impl<T: DeserializeOwned + Send + 'static> EventStream<T> {
+                    pub fn lines(body: hyper::Body) -> Self {
+                        Self::delimited(body, "\n")
+                    }
+                    $['\n']
+                    #[doc = "Reads a JSON text sequence (RFC 7464), whose records each start with an RS character"]
+                    pub fn records(body: hyper::Body) -> Self {
+                        Self::delimited(body, "\x1e")
+                    }
+                    $['\n']
+                    fn delimited(body: hyper::Body, delimiter: &'static str) -> Self {
+                        let inner = Self::split(body, delimiter)
+                            .filter(|line| future::ready(!matches!(line, Ok(line) if line.iter().all(|b| b.is_ascii_whitespace()))))
+                            .map(|line| line.and_then(|line| Self::item(&line)))
+                            .boxed();
+                        Self { inner }
+                    }
+                    $['\n']
+                    pub fn events(body: hyper::Body) -> Self {
+                        let inner = Self::split(body, "\n\n")
+                            .filter_map(|event| {
+                                future::ready(match event {
+                                    Ok(event) => {
+                                        let event = String::from_utf8_lossy(&event).into_owned();
+                                        let data = event
+                                            .lines()
+                                            .filter_map(|line| line.strip_prefix("data:"))
+                                            .map(|data| data.strip_prefix(' ').unwrap_or(data))
+                                            .collect::<Vec<_>>();
+                                        if data.is_empty() {
+                                            None
+                                        } else {
+                                            Some(Self::item(&Bytes::from(data.join("\n"))))
+                                        }
+                                    }
+                                    Err(error) => Some(Err(error)),
+                                })
+                            })
+                            .boxed();
+                        Self { inner }
+                    }
+                    $['\n']
+                    fn item(data: &Bytes) -> Result<T, Error> {
+                        decode(data).or_else(|error| {
+                            serde_json::from_value(Value::String(String::from_utf8_lossy(data).into_owned()))
+                                .map_err(|_| error)
+                        })
+                    }
+                    $['\n']
+                    fn split(body: hyper::Body, delimiter: &'static str) -> BoxStream<'static, Result<Bytes, Error>> {
+                        stream::unfold((body, Vec::new(), false), move |(mut body, mut buffer, mut done)| async move {
+                            loop {
+                                let delimiter = delimiter.as_bytes();
+                                if let Some(index) = buffer.windows(delimiter.len()).position(|window| window == delimiter) {
+                                    let chunk = buffer.drain(..index + delimiter.len()).take(index).collect::<Vec<u8>>();
+                                    return Some((Ok(Bytes::from(chunk)), (body, buffer, done)));
+                                }
+                                if done {
+                                    if buffer.is_empty() {
+                                        return None;
+                                    }
+                                    let chunk = std::mem::take(&mut buffer);
+                                    return Some((Ok(Bytes::from(chunk)), (body, buffer, done)));
+                                }
+                                match body.next().await {
+                                    Some(Ok(data)) => buffer.extend(data.iter().filter(|b| **b != b'\r')),
+                                    Some(Err(error)) => return Some((Err(Error::from(error)), (body, buffer, true))),
+                                    None => done = true,
+                                }
+                            }
+                        })
+                        .boxed()
+                    }
+                }
+                $['\n']
+                impl<T> futures_util::Stream for EventStream<T> {
+                    type Item = Result<T, Error>;
+                    $['\n']
+                    fn poll_next(
+                        mut self: std::pin::Pin<&mut Self>,
+                        cx: &mut std::task::Context,
+                    ) -> std::task::Poll<Option<Self::Item>> {
+                        self.inner.poll_next_unpin(cx)
+                    }
+                }
+                $['\n']
+                impl<T> std::fmt::Debug for EventStream<T> {
+                    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                        f.debug_struct("EventStream").finish_non_exhaustive()
+                    }
+                }
+                $['\n']
+                #[derive(Debug)]
+                pub struct ApiResponse<T, H = ()> {
+                    pub status: StatusCode,
+                    pub headers: HeaderMap,
+                    pub typed_headers: H,
+                    pub body: T,
+                }
+                $['\n']
+                impl<T: DeserializeOwned> ApiResponse<T> {
+                    pub async fn decode(response: hyper::Response<hyper::Body>) -> Result<Self, Error> {
+                        let (parts, body) = response.into_parts();
+                        let body = hyper::body::to_bytes(body).await?;
+                        Ok(Self {
+                            status: parts.status,
+                            headers: parts.headers,
+                            typed_headers: (),
+                            body: decode(&body)?,
+                        })
+                    }
+                }
+                $['\n']
+                impl ApiResponse<()> {
+                    pub fn metadata(response: hyper::Response<hyper::Body>) -> Self {
+                        let (parts, _) = response.into_parts();
+                        Self {
+                            status: parts.status,
+                            headers: parts.headers,
+                            typed_headers: (),
+                            body: (),
+                        }
+                    }
+                }
+                $['\n']
+                impl<T> ApiResponse<T> {
+                    pub(crate) fn typed<H>(self) -> Result<ApiResponse<T, H>, Error>
+                    where
+                        H: for<'a> TryFrom<&'a HeaderMap, Error = Error>,
+                    {
+                        Ok(ApiResponse {
+                            typed_headers: H::try_from(&self.headers)?,
+                            status: self.status,
+                            headers: self.headers,
+                            body: self.body,
+                        })
+                    }
+                }
+                $['\n']
+                impl<T, H> ApiResponse<T, H> {
+                    #[doc = "Wraps a body and its typed headers, as returned by `ApiService` methods; the router answers with the status documented for the body and adds `headers` to the reply"]
+                    pub fn new(body: T, typed_headers: H) -> Self {
+                        Self {
+                            status: StatusCode::OK,
+                            headers: HeaderMap::new(),
+                            typed_headers,
+                            body,
+                        }
+                    }
+                }
+                $['\n']
+                #[derive(Debug)]
+                pub enum ApiError {
+                    $(for ty in &errors =>
+                        $(model_cfg(ty))
+                        $ty(ApiResponse<$(import("super", ty))>),
+                    )
+                }
+                $['\n']
+                impl ApiError {
+                    pub fn status(&self) -> StatusCode {
+                        match *self {
+                            $(for ty in &errors =>
+                                $(model_cfg(ty))
+                                ApiError::$ty(ref response) => response.status,
+                            )
+                        }
+                    }
+                    $['\n']
+                    pub fn headers(&self) -> &HeaderMap {
+                        match *self {
+                            $(for ty in &errors =>
+                                $(model_cfg(ty))
+                                ApiError::$ty(ref response) => &response.headers,
+                            )
+                        }
+                    }
+                }
+                $['\n']
+                impl std::fmt::Display for ApiError {
+                    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                        match *self {
+                            $(for ty in &errors =>
+                                $(model_cfg(ty))
+                                ApiError::$ty(ref response) => write!(f, "{}: {:?}", response.status, response.body),
+                            )
+                        }
+                    }
+                }
+                $['\n']
+                impl std::error::Error for ApiError {}
+                $['\n']
+                pub struct Payload {
+                    content_type: String,
+                    body: PayloadBody,
+                }
+                $['\n']
+                impl Payload {
+                    pub fn json<T: Serialize>(value: &T) -> Result<Self, Error> {
+                        let body = serde_json::to_vec(value).map_err(|e| Error::Serialize(Box::new(e)))?;
+                        Ok(Self {
+                            content_type: "application/json".to_string(),
+                            body: PayloadBody::Bytes(body.into()),
+                        })
+                    }
+                    $['\n']
+                    pub fn form<T: Serialize>(value: &T) -> Result<Self, Error> {
+                        Ok(Self {
+                            content_type: "application/x-www-form-urlencoded".to_string(),
+                            body: PayloadBody::Bytes(serde_urlencoded::to_string(value)?.into()),
+                        })
+                    }
+                    $['\n']
+                    pub fn binary<S: AsRef<str>>(content_type: S, body: hyper::Body) -> Self {
+                        Self {
+                            content_type: content_type.as_ref().to_string(),
+                            body: PayloadBody::Stream(body),
+                        }
+                    }
+                    $['\n']
+                    fn try_clone(&self) -> Option<Self> {
+                        match &self.body {
+                            PayloadBody::Bytes(bytes) => Some(Self {
+                                content_type: self.content_type.clone(),
+                                body: PayloadBody::Bytes(bytes.clone()),
+                            }),
+                            PayloadBody::Stream(_) => None,
+                        }
+                    }
+                    $['\n']
+                    fn into_body(self) -> hyper::Body {
+                        match self.body {
+                            PayloadBody::Bytes(bytes) => hyper::Body::from(bytes),
+                            PayloadBody::Stream(body) => body,
+                        }
+                    }
+                }
+                $['\n']
+                enum PayloadBody {
+                    Bytes(Bytes),
+                    Stream(hyper::Body),
+                }
+                $['\n']
+                pub struct Part {
+                    body: PayloadBody,
+                    file_name: Option<String>,
+                    content_type: Option<String>,
+                }
+                $['\n']
+                impl Part {
+                    pub fn text<S: Into<String>>(value: S) -> Self {
+                        Self::bytes(value.into())
+                    }
+                    $['\n']
+                    pub fn bytes<B: Into<Bytes>>(value: B) -> Self {
+                        Self {
+                            body: PayloadBody::Bytes(value.into()),
+                            file_name: None,
+                            content_type: None,
+                        }
+                    }
+                    $['\n']
+                    pub fn stream(body: hyper::Body) -> Self {
+                        Self {
+                            body: PayloadBody::Stream(body),
+                            file_name: None,
+                            content_type: None,
+                        }
+                    }
+                    $['\n']
+                    pub fn file_name<S: Into<String>>(mut self, file_name: S) -> Self {
+                        self.file_name = Some(file_name.into());
+                        self
+                    }
+                    $['\n']
+                    pub fn content_type<S: Into<String>>(mut self, content_type: S) -> Self {
+                        self.content_type = Some(content_type.into());
+                        self
+                    }
+                }
+                $['\n']
+                #[derive(Default)]
+                pub struct Multipart {
+                    parts: Vec<(String, Part)>,
+                }
+                $['\n']
+                impl Multipart {
+                    pub fn new() -> Self {
+                        Self::default()
+                    }
+                    $['\n']
+                    pub fn text<N: Into<String>, S: Into<String>>(self, name: N, value: S) -> Self {
+                        self.part(name, Part::text(value))
+                    }
+                    $['\n']
+                    pub fn part<N: Into<String>>(mut self, name: N, part: Part) -> Self {
+                        self.parts.push((name.into(), part));
+                        self
+                    }
+                    $['\n']
+                    pub fn into_payload(self) -> Payload {
+                        let boundary = format!(
+                            "colmenar-{:x}",
+                            std::time::SystemTime::now()
+                                .duration_since(std::time::UNIX_EPOCH)
+                                .map(|d| d.as_nanos())
+                                .unwrap_or_default()
+                        );
+                        let chunk = |value: String| -> BoxStream<'static, Result<Bytes, hyper::Error>> {
+                            stream::once(future::ready(Ok(Bytes::from(value)))).boxed()
+                        };
+                        let mut chunks = Vec::new();
+                        let escape = |value: &str| value.replace('"', "%22").replace('\r', "%0D").replace('\n', "%0A");
+                        for (name, part) in self.parts {
+                            let mut head = format!("--{boundary}\r\nContent-Disposition: form-data; name=\"{}\"", escape(&name));
+                            if let Some(file_name) = part.file_name {
+                                head.push_str(&format!("; filename=\"{}\"", escape(&file_name)));
+                            }
+                            head.push_str("\r\n");
+                            if let Some(content_type) = part.content_type {
+                                head.push_str(&format!("Content-Type: {content_type}\r\n"));
+                            }
+                            head.push_str("\r\n");
+                            chunks.push(chunk(head));
+                            chunks.push(match part.body {
+                                PayloadBody::Bytes(bytes) => stream::once(future::ready(Ok(bytes))).boxed(),
+                                PayloadBody::Stream(body) => body.boxed(),
+                            });
+                            chunks.push(chunk("\r\n".to_string()));
+                        }
+                        chunks.push(chunk(format!("--{boundary}--\r\n")));
+                        Payload {
+                            content_type: format!("multipart/form-data; boundary={boundary}"),
+                            body: PayloadBody::Stream(hyper::Body::wrap_stream(stream::iter(chunks).flatten())),
+                        }
+                    }
+                }
+                $['\n']
+                $(for operation in Operation::all() =>
+                    $(operation.body_tokens())
+                    $(operation.response_tokens())
+                    $(operation.headers_tokens())
+                    $['\n']
+                )
+                $(Security::tokens())
+                $['\n']
+                $(Server::tokens(self.schema.servers.first()))
+                $['\n']
+                #[doc = "Sends requests for `Api`, implemented for hyper clients and open to other HTTP stacks or test doubles"]
+                #[doc = ""]
+                #[doc = "Implementations report failures to connect or send as `Error::Connection` so they are retried."]
+                pub trait HttpTransport: Send + Sync {
+                    fn send(
+                        &self,
+                        request: hyper::Request<hyper::Body>,
+                    ) -> BoxFuture<'static, Result<hyper::Response<hyper::Body>, Error>>;
+                }
+                $['\n']
+                impl<C: Connect + Clone + Send + Sync + 'static> HttpTransport for Client<C> {
+                    fn send(
+                        &self,
+                        request: hyper::Request<hyper::Body>,
+                    ) -> BoxFuture<'static, Result<hyper::Response<hyper::Body>, Error>> {
+                        self.request(request).map(|response| response.map_err(Error::from)).boxed()
+                    }
+                }
+                $['\n']
+                #[doc = "Connects directly, or through an HTTP proxy with a CONNECT tunnel"]
+                #[derive(Clone)]
+                pub struct Connector {
+                    http: HttpConnector,
+                    proxy: Option<Uri>,
+                }
+                $['\n']
+                impl Service<Uri> for Connector {
+                    type Response = TcpStream;
+                    type Error = Box<dyn std::error::Error + Send + Sync>;
+                    type Future = BoxFuture<'static, Result<TcpStream, Self::Error>>;
+                    $['\n']
+                    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+                        self.http.poll_ready(cx).map_err(Into::into)
+                    }
+                    $['\n']
+                    fn call(&mut self, uri: Uri) -> Self::Future {
+                        let mut http = self.http.clone();
+                        let proxy = self.proxy.clone();
+                        async move {
+                            let proxy = match proxy {
+                                Some(proxy) => proxy,
+                                None => return Ok(http.call(uri).await?),
+                            };
+                            let mut stream = http.call(proxy).await?;
+                            let host = uri.host().ok_or("missing host")?;
+                            let port = uri.port_u16().unwrap_or(match uri.scheme_str() {
+                                Some("https") => 443,
+                                _ => 80,
+                            });
+                            stream
+                                .write_all(format!("CONNECT {host}:{port} HTTP/1.1\r\nHost: {host}:{port}\r\n\r\n").as_bytes())
+                                .await?;
+                            let mut head = Vec::new();
+                            let mut byte = [0u8; 1];
+                            while !head.ends_with(b"\r\n\r\n") {
+                                if stream.read(&mut byte).await? == 0 {
+                                    return Err("proxy closed the connection".into());
+                                }
+                                head.extend_from_slice(&byte);
+                            }
+                            let head = String::from_utf8_lossy(&head);
+                            let status = head.lines().next().unwrap_or_default();
+                            if !status.split_whitespace().nth(1).map_or(false, |code| code.starts_with('2')) {
+                                return Err(format!("proxy refused to connect: {status}").into());
+                            }
+                            Ok(stream)
+                        }
+                        .boxed()
+                    }
+                }
+                $['\n']
+                #[doc = "Retries transient failures, by default only for idempotent operations"]
+                #[derive(Debug, Clone)]
+                pub struct RetryPolicy {
+                    #[doc = "Attempts in total, including the first one"]
+                    pub max_attempts: u32,
+                    pub initial_backoff: Duration,
+                    #[doc = "Longest wait between attempts, a longer `Retry-After` ends the retries"]
+                    pub max_backoff: Duration,
+                    #[doc = "Waits a random duration between half and all of the backoff"]
+                    pub jitter: bool,
+                }
+                $['\n']
+                impl Default for RetryPolicy {
+                    fn default() -> Self {
+                        Self {
+                            max_attempts: 3,
+                            initial_backoff: Duration::from_millis(200),
+                            max_backoff: Duration::from_secs(30),
+                            jitter: true,
+                        }
+                    }
+                }
+                $['\n']
+                impl RetryPolicy {
+                    pub fn none() -> Self {
+                        Self {
+                            max_attempts: 1,
+                            ..Self::default()
+                        }
+                    }
+                    $['\n']
+                    pub fn backoff(&self, attempt: u32) -> Duration {
+                        let backoff = self
+                            .initial_backoff
+                            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+                            .min(self.max_backoff);
+                        if !self.jitter {
+                            return backoff;
+                        }
+                        let random = RandomState::new().build_hasher().finish();
+                        backoff / 2 + backoff.mul_f64((random % 1000) as f64 / 2000.0)
+                    }
+                    $['\n']
+                    fn delay(
+                        &self,
+                        attempt: u32,
+                        result: &Result<hyper::Response<hyper::Body>, Error>,
+                    ) -> Option<Duration> {
+                        let response = match result {
+                            Ok(response) => response,
+                            Err(error) if error.is_retryable() => return Some(self.backoff(attempt)),
+                            Err(_) => return None,
+                        };
+                        if !matches!(response.status().as_u16(), 408 | 429 | 500 | 502 | 503 | 504) {
+                            return None;
+                        }
+                        let retry_after = response
+                            .headers()
+                            .get("retry-after")
+                            .and_then(|value| value.to_str().ok())
+                            .and_then(|value| match value.trim().parse::<u64>() {
+                                Ok(seconds) => Some(Duration::from_secs(seconds)),
+                                Err(_) => chrono::DateTime::parse_from_rfc2822(value)
+                                    .ok()
+                                    .map(|date| (date.with_timezone(&chrono::Utc) - chrono::Utc::now())
+                                        .to_std()
+                                        .unwrap_or_default()),
+                            });
+                        match retry_after {
+                            Some(delay) if delay > self.max_backoff => None,
+                            Some(delay) => Some(delay),
+                            None => Some(self.backoff(attempt)),
+                        }
+                    }
+                }
+                $['\n']
+                #[doc = "Hooks around every request, including retries, in the order added to the builder"]
+                pub trait Middleware: Send + Sync {
+                    #[doc = "Runs after credentials are applied, just before the request is sent"]
+                    fn before_request<'a>(
+                        &'a self,
+                        request: &'a mut hyper::Request<hyper::Body>,
+                    ) -> BoxFuture<'a, Result<(), Error>> {
+                        let _ = request;
+                        future::ready(Ok(())).boxed()
+                    }
+                    $['\n']
+                    #[doc = "Runs in reverse order once response headers arrive, before the body is read"]
+                    fn after_response<'a>(
+                        &'a self,
+                        response: &'a mut hyper::Response<hyper::Body>,
+                    ) -> BoxFuture<'a, Result<(), Error>> {
+                        let _ = response;
+                        future::ready(Ok(())).boxed()
+                    }
+                }
+                $['\n']
+                pub struct ApiBuilder {
+                    provider: Arc<dyn CredentialProvider>,
+                    endpoint: String,
+                    pinned: bool,
+                    user_agent: String,
+                    headers: HeaderMap,
+                    connect_timeout: Option<Duration>,
+                    timeout: Option<Duration>,
+                    http2_only: bool,
+                    proxy: Option<Uri>,
+                    retry: RetryPolicy,
+                    middleware: Vec<Arc<dyn Middleware>>,
+                    log_bodies: bool,
+                    transport: Option<Arc<dyn HttpTransport>>,
+                }
+                $['\n']
+                impl ApiBuilder {
+                    #[doc = "Sends every request to `server`, except operations that declare their own servers"]
+                    pub fn server(mut self, server: Server) -> Self {
+                        self.endpoint = server.url();
+                        self.pinned = false;
+                        self
+                    }
+                    $['\n']
+                    #[doc = "Sends every request to `endpoint`, including operations that declare their own servers"]
+                    pub fn endpoint<S: Into<String>>(mut self, endpoint: S) -> Self {
+                        self.endpoint = endpoint.into();
+                        self.pinned = true;
+                        self
+                    }
+                    $['\n']
+                    pub fn user_agent<S: Into<String>>(mut self, user_agent: S) -> Self {
+                        self.user_agent = user_agent.into();
+                        self
+                    }
+                    $['\n']
+                    #[doc = "Adds a header to every request that credentials, the payload or the user agent did not already set"]
+                    pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+                        self.headers.append(name, value);
+                        self
+                    }
+                    $['\n']
+                    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+                        self.connect_timeout = Some(timeout);
+                        self
+                    }
+                    $['\n']
+                    #[doc = "Limits the time until response headers arrive, failing with `Error::Timeout`"]
+                    pub fn timeout(mut self, timeout: Duration) -> Self {
+                        self.timeout = Some(timeout);
+                        self
+                    }
+                    $['\n']
+                    #[doc = "Speaks HTTP/2 with prior knowledge instead of HTTP/1.1"]
+                    pub fn http2_only(mut self, enabled: bool) -> Self {
+                        self.http2_only = enabled;
+                        self
+                    }
+                    $['\n']
+                    #[doc = "Tunnels every connection through an HTTP proxy such as `http://proxy:3128`"]
+                    pub fn proxy(mut self, proxy: Uri) -> Self {
+                        self.proxy = Some(proxy);
+                        self
+                    }
+                    $['\n']
+                    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+                        self.retry = retry;
+                        self
+                    }
+                    $['\n']
+                    pub fn middleware<M: Middleware + 'static>(mut self, middleware: M) -> Self {
+                        self.middleware.push(Arc::new(middleware));
+                        self
+                    }
+                    $['\n']
+                    #[doc = "Logs request and response bodies as `debug` events, off by default as they may hold secrets"]
+                    pub fn log_bodies(mut self, enabled: bool) -> Self {
+                        self.log_bodies = enabled;
+                        self
+                    }
+                    $['\n']
+                    #[doc = "Sends requests with another transport, such as a pre-built hyper `Client`, ignoring the connection settings above"]
+                    pub fn transport<T: HttpTransport + 'static>(mut self, transport: T) -> Self {
+                        self.transport = Some(Arc::new(transport));
+                        self
+                    }
+                    $['\n']
+                    pub fn build(self) -> Api {
+                        let transport = match self.transport {
+                            Some(transport) => transport,
+                            None => {
+                                let mut http = HttpConnector::new();
+                                $(&enforce_http)
+                                http.set_connect_timeout(self.connect_timeout);
+                                let connector = Connector {
+                                    http,
+                                    proxy: self.proxy,
+                                };
+                                $(&tls)
+                                Arc::new(
+                                    Client::builder()
+                                        .http2_only(self.http2_only)
+                                        .build::<_, hyper::Body>(connector),
+                                )
+                            }
+                        };
+                        Api {
+                            version: $(quoted(&self.schema.info.version)).to_string(),
+                            endpoint: self.endpoint,
+                            pinned: self.pinned,
+                            provider: self.provider,
+                            tokens: Mutex::new(BTreeMap::new()),
+                            user_agent: self.user_agent,
+                            headers: self.headers,
+                            timeout: self.timeout,
+                            retry: self.retry,
+                            middleware: self.middleware,
+                            log_bodies: self.log_bodies,
+                            transport,
+                        }
+                    }
+                }
+                $['\n']
                 pub struct Api {
                     version: String,
                     endpoint: String,
-                    token: String,
-                    hub: String,
-                    client: Client<HttpsConnector<HttpConnector>>,
+                    pinned: bool,
+                    provider: Arc<dyn CredentialProvider>,
+                    tokens: Mutex<BTreeMap<String, (String, Instant)>>,
+                    user_agent: String,
+                    headers: HeaderMap,
+                    timeout: Option<Duration>,
+                    retry: RetryPolicy,
+                    middleware: Vec<Arc<dyn Middleware>>,
+                    log_bodies: bool,
+                    transport: Arc<dyn HttpTransport>,
                 }
                 $['\n']
                 impl Api {
-                    pub fn new<S: AsRef<str>>(token: S, hub: S) -> Self {
-                        let https = HttpsConnector::new();
-                        let client = Client::builder().build::<_, hyper::Body>(https);
-                        Self {
-                            version: $(quoted(&self.schema.info.version)).to_string(),
-                            endpoint: $(quoted(&self.schema.servers[0].url)).to_string(),
-                            token: token.as_ref().to_string(),
-                            hub: hub.as_ref().to_string(),
-                            client,
+                    pub fn new<P: CredentialProvider + 'static>(provider: P) -> Self {
+                        Api::builder(provider).build()
+                    }
+                    $['\n']
+                    pub fn builder<P: CredentialProvider + 'static>(provider: P) -> ApiBuilder {
+                        ApiBuilder {
+                            provider: Arc::new(provider),
+                            endpoint: Server::default().url(),
+                            pinned: false,
+                            user_agent: $(quoted(&user_agent)).to_string(),
+                            headers: HeaderMap::new(),
+                            connect_timeout: None,
+                            timeout: None,
+                            http2_only: false,
+                            proxy: None,
+                            retry: RetryPolicy::default(),
+                            middleware: Vec::new(),
+                            log_bodies: false,
+                            transport: None,
                         }
                     }
                     $['\n']
-                    pub async fn request<S: AsRef<str>, SS: Serialize, DD: DeserializeOwned>(&self, method: Method, path: S, body: Option<SS>) -> Result<Option<DD>, Error> {
+                    pub async fn request<S: AsRef<str>>(
+                        &self,
+                        method: Method,
+                        server: Option<Server>,
+                        path: S,
+                        security: &[&[&str]],
+                        retryable: bool,
+                        mut payload: Option<Payload>,
+                    ) -> Result<hyper::Response<hyper::Body>, Error> {
                         let path = path.as_ref();
-                        let body = body.map(|v| serde_json::to_value(v).unwrap());
-                        let uri = format!("{}{path}", self.endpoint).parse::<Uri>().unwrap();
-                        println!("Request: {} {}", method, uri);
+                        let endpoint = match server {
+                            Some(server) if !self.pinned => server.url(),
+                            _ => self.endpoint.clone(),
+                        };
+                        let mut credentials = self.provider.credentials().await?;
+                        let mut refreshed = false;
+                        let mut attempt = 1;
+                        loop {
+                            let replay = match &payload {
+                                Some(payload) => payload.try_clone().map(Some),
+                                None => Some(None),
+                            };
+                            let result = self
+                                .send(&credentials, method.clone(), &endpoint, path, security, payload)
+                                .await;
+                            payload = match replay {
+                                Some(payload) => payload,
+                                None => return result,
+                            };
+                            match &result {
+                                Ok(response)
+                                    if response.status() == StatusCode::UNAUTHORIZED
+                                        && !security.is_empty()
+                                        && !refreshed =>
+                                {
+                                    refreshed = true;
+                                    self.tokens.lock().unwrap().clear();
+                                    credentials = self.provider.refresh().await?;
+                                }
+                                _ => {
+                                    if !retryable || attempt >= self.retry.max_attempts {
+                                        return result;
+                                    }
+                                    let delay = match self.retry.delay(attempt, &result) {
+                                        Some(delay) => delay,
+                                        None => return result,
+                                    };
+                                    tokio::time::sleep(delay).await;
+                                    attempt += 1;
+                                }
+                            }
+                        }
+                    }
+                    $['\n']
+                    async fn send(
+                        &self,
+                        credentials: &Credentials,
+                        method: Method,
+                        endpoint: &str,
+                        path: &str,
+                        security: &[&[&str]],
+                        payload: Option<Payload>,
+                    ) -> Result<hyper::Response<hyper::Body>, Error> {
+                        let mut uri = format!("{endpoint}{path}");
                         let request = hyper::Request::builder()
-                            .method(method)
-                            .uri(uri)
-                            .header("user-agent", "cycle/1.0.0")
-                            .header("authorization", format!("Bearer {}", self.token))
-                            .header("x-hub-id", &self.hub)
-                            .body(match body {
-                                Some(body) => hyper::Body::from(serde_json::to_string(&body)?),
-                                None => hyper::Body::empty(),
-                            })
-                            .unwrap();
-                        let response = match self.client.request(request).await {
+                            .method(method.clone())
+                            .header("user-agent", &self.user_agent);
+                        let mut request = self.authorize(credentials, security, &mut uri, request).await?;
+                        let uri = uri
+                            .parse::<Uri>()
+                            .map_err(|e| Error::Validation(e.to_string()))?;
+                        request = request.uri(uri);
+                        let body = match payload {
+                            Some(payload) => {
+                                request = request.header("content-type", &payload.content_type);
+                                if let (true, PayloadBody::Bytes(body)) = (self.log_bodies, &payload.body) {
+                                    tracing::debug!(body = %String::from_utf8_lossy(body), "request body");
+                                }
+                                payload.into_body()
+                            }
+                            None => hyper::Body::empty(),
+                        };
+                        let mut request = request
+                            .body(body)
+                            .map_err(|e| Error::Validation(e.to_string()))?;
+                        for name in self.headers.keys() {
+                            if !request.headers().contains_key(name) {
+                                for value in self.headers.get_all(name) {
+                                    request.headers_mut().append(name, value.clone());
+                                }
+                            }
+                        }
+                        for middleware in self.middleware.iter() {
+                            middleware.before_request(&mut request).await?;
+                        }
+                        tracing::debug!(
+                            method = %request.method(),
+                            uri = %redact_uri(request.uri()),
+                            headers = ?redact_headers(request.headers()),
+                            "sending request"
+                        );
+                        let response = self.transport.send(request);
+                        let response = match self.timeout {
+                            Some(timeout) => match tokio::time::timeout(timeout, response).await {
+                                Ok(response) => response,
+                                Err(_) => return Err(Error::Timeout),
+                            },
+                            None => response.await,
+                        };
+                        let mut response = match response {
                             Ok(resp) => resp,
                             Err(e) => {
-                                println!("Error: {e:#?}");
+                                tracing::debug!(error = %e, "request failed");
                                 return Err(Error::from(e));
                             }
                         };
-                        println!("Response: {:?}", response);
-                        let body = hyper::body::to_bytes(response.into_body()).await?;
-                        println!("Body: {:?}", String::from_utf8_lossy(&body));
-                        Ok(match serde_json::from_slice(&body) {
-                            Ok(v) => Some(serde_json::from_value(v)?),
-                            Err(e) => None,
-                        })
+                        tracing::Span::current().record("status", response.status().as_u16());
+                        tracing::debug!(
+                            status = response.status().as_u16(),
+                            headers = ?redact_headers(response.headers()),
+                            "received response"
+                        );
+                        if self.log_bodies {
+                            response.extensions_mut().insert(LogBody);
+                        }
+                        for middleware in self.middleware.iter().rev() {
+                            middleware.after_response(&mut response).await?;
+                        }
+                        Ok(response)
                     }
                     $['\n']
-                    $(for operation in Operation::all() =>
-                        $(operation.tokens()?)
+                    $(Security::authorize())
+                    $['\n']
+                    $(for operation in Operation::all().iter().filter(|operation| operation.tag.is_none()) =>
+                        $(operation.tokens(&operation.name, quote!(self))?)
+                        $['\n']
+                        $(operation.stream_tokens(&operation.name, quote!(self), quote!('_))?)
                         $['\n']
                     )
                 }
@@ -8,7 +8,7 @@ impl Array {
         Ok(match &schema.schema_kind {
             SchemaKind::Type(Type::Array(array)) => match array.items.as_ref().unwrap() {
                 ReferenceOr::Reference { reference, .. } => {
-                    let ty = reference.split("/").last().unwrap().to_string();
+                    let ty = Model::reference(reference);
                     quote!(Vec<$ty>)
                 }
                 ReferenceOr::Item(item) => match &item.schema_kind {
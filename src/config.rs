@@ -0,0 +1,45 @@
+use crate::prelude::*;
+use once_cell::sync::OnceCell;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+static CONFIG: OnceCell<Config> = OnceCell::new();
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub rename: BTreeMap<String, String>,
+    pub layout: Layout,
+    #[serde(rename = "crate")]
+    pub package: Option<Package>,
+    pub blocking: bool,
+    pub mock: bool,
+    pub service: bool,
+    pub pagination: BTreeMap<String, Pagination>,
+}
+
+impl Config {
+    pub fn load(path: &str) -> Result<Self, Error> {
+        let source = match std::fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(e) => {
+                return err!("Error: could not read config {}: {}", path, e);
+            }
+        };
+        match serde_yaml::from_str(&source) {
+            Ok(config) => Ok(config),
+            Err(e) => err!("Error: could not parse config {}: {}", path, e),
+        }
+    }
+
+    pub fn set(config: Config) -> Result<(), Error> {
+        if CONFIG.set(config).is_err() {
+            return err!("Error: config has already been set");
+        }
+        Ok(())
+    }
+
+    pub fn get() -> &'static Config {
+        CONFIG.get_or_init(Config::default)
+    }
+}
@@ -10,3 +10,60 @@ pub const GREEK: [&'static str; 24] = [
     "lambda", "mu", "nu", "xi", "omicron", "pi", "rho", "sigma", "tau", "upsilon", "phi", "chi",
     "psi", "omega",
 ];
+
+pub const RESERVED: [&'static str; 26] = [
+    "Api",
+    "ApiBuilder",
+    "ApiError",
+    "ApiResponse",
+    "ApiService",
+    "BasicAuth",
+    "CallbackCredentials",
+    "ClientCredentials",
+    "Connector",
+    "CredentialProvider",
+    "Credentials",
+    "EnvCredentials",
+    "Error",
+    "EventStream",
+    "HttpTransport",
+    "Middleware",
+    "MockRequest",
+    "MockResponse",
+    "MockServer",
+    "Multipart",
+    "Part",
+    "Payload",
+    "RetryPolicy",
+    "Router",
+    "Server",
+    "ServiceError",
+];
+
+pub const RESERVED_MODULES: [&'static str; 13] = [
+    "access_token",
+    "api",
+    "authorize",
+    "block_on",
+    "blocking",
+    "builder",
+    "error",
+    "mock",
+    "model",
+    "new",
+    "request",
+    "send",
+    "service",
+];
+
+pub const RESERVED_FEATURES: [&'static str; 6] =
+    ["default", "full", "mock", "native_tls", "rustls", "service"];
+
+pub const RESERVED_FIELDS: [&'static str; 6] = [
+    "clone",
+    "credentials",
+    "default",
+    "from_env",
+    "has",
+    "refresh",
+];
@@ -0,0 +1,143 @@
+use crate::prelude::*;
+use indexmap::IndexMap;
+use once_cell::sync::OnceCell;
+use openapiv3::{MediaType, ReferenceOr, RequestBody};
+use std::{collections::BTreeMap, sync::Mutex};
+
+static REQUEST_BODIES: OnceCell<Mutex<BTreeMap<String, RequestBody>>> = OnceCell::new();
+
+#[derive(Clone)]
+pub enum Content {
+    Json(Tokens),
+    Form(Tokens),
+    Multipart,
+    Binary { variant: String, media_type: String },
+}
+
+impl Content {
+    pub fn add_request_body(name: &str, body: &RequestBody) {
+        REQUEST_BODIES
+            .get_or_init(|| Mutex::new(BTreeMap::new()))
+            .lock()
+            .unwrap()
+            .insert(name.to_string(), body.clone());
+    }
+
+    pub fn request_body(schema: &ReferenceOr<RequestBody>) -> Result<RequestBody, Error> {
+        match schema {
+            ReferenceOr::Reference { reference, .. } => {
+                let reference = reference.split('/').last().unwrap();
+                match REQUEST_BODIES
+                    .get_or_init(|| Mutex::new(BTreeMap::new()))
+                    .lock()
+                    .unwrap()
+                    .get(reference)
+                {
+                    Some(body) => Ok(body.clone()),
+                    None => err!("Unknown request body reference: {reference}"),
+                }
+            }
+            ReferenceOr::Item(body) => Ok(body.clone()),
+        }
+    }
+
+    pub fn discover(name: &str, content: &IndexMap<String, MediaType>) -> Result<Vec<Self>, Error> {
+        let mut contents: Vec<Content> = Vec::new();
+        for (media_type, media) in content.iter() {
+            let media_type = media_type.split(';').next().unwrap().trim().to_lowercase();
+            let variant = if media_type == "application/json" || media_type.ends_with("+json") {
+                "json"
+            } else if media_type == "application/x-www-form-urlencoded" {
+                "form"
+            } else if media_type == "multipart/form-data" {
+                "multipart"
+            } else {
+                "binary"
+            };
+            if variant != "binary"
+                && contents
+                    .iter()
+                    .any(|content| content.variant().to_lowercase() == variant)
+            {
+                continue;
+            }
+            let type_name = if content.len() == 1 {
+                format!("{name}_request")
+            } else {
+                format!("{name}_{variant}_request")
+            }
+            .to_upper_camel_case();
+            contents.push(match variant {
+                "json" => Content::Json(Self::discover_type(&type_name, media)?),
+                "form" => Content::Form(Self::discover_type(&type_name, media)?),
+                "multipart" => Content::Multipart,
+                _ => Content::Binary {
+                    variant: "Binary".to_string(),
+                    media_type,
+                },
+            });
+        }
+        let binaries = contents
+            .iter()
+            .filter(|content| matches!(content, Content::Binary { .. }))
+            .count();
+        if binaries > 1 {
+            for content in contents.iter_mut() {
+                if let Content::Binary {
+                    variant,
+                    media_type,
+                } = content
+                {
+                    let name = media_type.to_upper_camel_case();
+                    if !name.is_empty() {
+                        *variant = name;
+                    }
+                }
+            }
+        }
+        Ok(contents)
+    }
+
+    fn discover_type(name: &str, media: &MediaType) -> Result<Tokens, Error> {
+        Ok(match media.schema.as_ref() {
+            None => quote!(Value),
+            Some(ReferenceOr::Reference { reference, .. }) => {
+                let module = import("super", Model::reference(reference));
+                quote!($module)
+            }
+            Some(ReferenceOr::Item(schema)) => {
+                Model::discover(name, schema)?;
+                let module = import("super", name);
+                quote!($module)
+            }
+        })
+    }
+
+    pub fn variant(&self) -> &str {
+        match self {
+            Content::Json(_) => "Json",
+            Content::Form(_) => "Form",
+            Content::Multipart => "Multipart",
+            Content::Binary { variant, .. } => variant,
+        }
+    }
+
+    pub fn ty(&self) -> Tokens {
+        match self {
+            Content::Json(ty) | Content::Form(ty) => ty.clone(),
+            Content::Multipart => quote!(Multipart),
+            Content::Binary { .. } => quote!(hyper::Body),
+        }
+    }
+
+    pub fn encode(&self, value: Tokens) -> Tokens {
+        match self {
+            Content::Json(_) => quote!(Payload::json(&$value)?),
+            Content::Form(_) => quote!(Payload::form(&$value)?),
+            Content::Multipart => quote!($value.into_payload()),
+            Content::Binary { media_type, .. } => {
+                quote!(Payload::binary($(quoted(media_type)), $value))
+            }
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use crate::prelude::*;
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    #[default]
+    Single,
+    Models,
+    Tags,
+    Namespaces,
+}
+
+impl Layout {
+    pub fn namespace(name: &str) -> Option<String> {
+        let (namespace, _) = name.rsplit_once('.')?;
+        let module = namespace.to_snake_case();
+        if ["api", "blocking", "error", "mock", "model", "service"].contains(&module.as_str())
+            || KEYWORDS.contains(&module.as_str())
+        {
+            Some(format!("{module}_models"))
+        } else {
+            Some(module)
+        }
+    }
+
+    pub fn modules(&self, components: &[String]) -> Result<BTreeMap<String, String>, Error> {
+        let mut fixed: BTreeMap<String, Option<String>> = BTreeMap::new();
+        let mut roots: Vec<(Option<String>, BTreeSet<String>)> = Vec::new();
+        match self {
+            Layout::Single | Layout::Models => return Ok(BTreeMap::new()),
+            Layout::Tags => {}
+            Layout::Namespaces => {
+                for name in components {
+                    fixed.insert(Model::type_name(name), Layout::namespace(name));
+                }
+            }
+        }
+        let graph = Model::graph()?;
+        for operation in Operation::all() {
+            let module = match self {
+                Layout::Tags => operation
+                    .tag
+                    .as_ref()
+                    .and_then(|tag| Tag::get(tag))
+                    .map(|tag| tag.module()),
+                _ => None,
+            };
+            roots.push((module, operation.dependencies()?));
+        }
+        for (name, module) in fixed.iter() {
+            if let Some(dependencies) = graph.get(name) {
+                roots.push((module.clone(), dependencies.clone()));
+            }
+        }
+        let reached = Model::reachability(&graph, &fixed, roots);
+        let mut modules = BTreeMap::new();
+        for (name, module) in fixed {
+            if let Some(module) = module {
+                modules.insert(name, module);
+            }
+        }
+        for (name, reached) in reached {
+            if let [Some(module)] = reached.into_iter().collect::<Vec<_>>().as_slice() {
+                modules.insert(name, module.clone());
+            }
+        }
+        modules.retain(|name, _| {
+            !RESERVED.contains(&name.as_str())
+                && !graph[name]
+                    .iter()
+                    .any(|dependency| RESERVED.contains(&dependency.as_str()))
+        });
+        Ok(modules)
+    }
+}
@@ -1,19 +1,41 @@
 mod api;
 mod array;
+mod config;
 mod constants;
+mod content;
 mod enumeration;
 mod error;
+mod layout;
+mod mock;
 mod model;
 mod operation;
+mod package;
+mod pagination;
 mod parameter;
 mod prelude;
 mod property;
+mod response;
+mod security;
+mod server;
+mod service;
+mod tag;
 
 pub use api::Api;
 pub use array::Array;
+pub use config::Config;
+pub use content::Content;
 pub use enumeration::Enumeration;
 pub use error::Error;
+pub use layout::Layout;
+pub use mock::Mock;
 pub use model::Model;
 pub use operation::Operation;
+pub use package::Package;
+pub use pagination::Pagination;
 pub use parameter::Parameter;
 pub use property::Property;
+pub use response::{Body, Header, Response, Status};
+pub use security::Security;
+pub use server::Server;
+pub use service::Service;
+pub use tag::Tag;
@@ -1,6 +1,29 @@
-use colmenar::{Api, Error};
+use colmenar::{Api, Config, Error};
+use log::{LevelFilter, Log, Metadata, Record};
+
+struct Logger;
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!("[{}] {}", record.level(), record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
 
 fn main() {
+    let level = std::env::var("RUST_LOG")
+        .ok()
+        .and_then(|level| level.parse().ok())
+        .unwrap_or(LevelFilter::Warn);
+    log::set_logger(&Logger).unwrap();
+    log::set_max_level(level);
     match run() {
         Ok(_) => {}
         Err(e) => {
@@ -11,10 +34,16 @@ fn main() {
 
 fn run() -> Result<(), Error> {
     let args: Vec<String> = std::env::args().collect();
-    if args.len() != 3 {
-        println!("Usage: {} <schema-file> <output-directory>", args[0]);
+    if args.len() != 3 && args.len() != 4 {
+        println!(
+            "Usage: {} <schema-file> <output-directory> [config-file]",
+            args[0]
+        );
         std::process::exit(1);
     }
+    if let Some(path) = args.get(3) {
+        Config::set(Config::load(path)?)?;
+    }
     Api::new(&args[1], &args[2])?.generate()?;
     Ok(())
 }
@@ -0,0 +1,585 @@
+use crate::prelude::*;
+use hyper::{
+    header::{HeaderName, HeaderValue},
+    Method,
+};
+use once_cell::sync::OnceCell;
+use openapiv3::{
+    Components, MediaType, OpenAPI, ParameterSchemaOrContent, ReferenceOr, Schema, SchemaKind,
+    StatusCode, StringFormat, Type, VariantOrUnknownOrEmpty,
+};
+use serde_json::{json, Map, Value};
+use std::{borrow::Borrow, collections::BTreeMap, str::FromStr, sync::Mutex};
+
+static MOCKS: OnceCell<Mutex<BTreeMap<String, Mock>>> = OnceCell::new();
+
+const DEPTH: usize = 8;
+
+#[derive(Clone)]
+pub struct Mock {
+    pub operation: String,
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Mock {
+    pub fn all() -> Vec<Mock> {
+        MOCKS
+            .get_or_init(|| Mutex::new(BTreeMap::new()))
+            .lock()
+            .unwrap()
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    fn add(mock: Mock) {
+        let mut mocks = MOCKS
+            .get_or_init(|| Mutex::new(BTreeMap::new()))
+            .lock()
+            .unwrap();
+        if mocks.contains_key(&mock.operation) {
+            panic!("Mock {} already exists", mock.operation);
+        }
+        mocks.insert(mock.operation.clone(), mock);
+    }
+
+    pub fn get(operation: &str) -> Option<Mock> {
+        MOCKS
+            .get_or_init(|| Mutex::new(BTreeMap::new()))
+            .lock()
+            .unwrap()
+            .get(operation)
+            .cloned()
+    }
+
+    pub fn discover(operation: &Operation, schema: &OpenAPI) -> Result<(), Error> {
+        let components = schema.components.clone().unwrap_or_default();
+        let item = match schema.paths.paths.get(&operation.path) {
+            Some(ReferenceOr::Item(item)) => item,
+            _ => return err!("Unknown path for mock: {}", operation.path),
+        };
+        let method = operation.method.clone();
+        let item = match method {
+            Method::GET => item.get.as_ref(),
+            Method::PUT => item.put.as_ref(),
+            Method::POST => item.post.as_ref(),
+            Method::DELETE => item.delete.as_ref(),
+            Method::OPTIONS => item.options.as_ref(),
+            Method::HEAD => item.head.as_ref(),
+            Method::PATCH => item.patch.as_ref(),
+            Method::TRACE => item.trace.as_ref(),
+            _ => None,
+        };
+        let item = match item {
+            Some(item) => item,
+            None => return err!("Unknown operation for mock: {method} {}", operation.path),
+        };
+        let response = item
+            .responses
+            .responses
+            .iter()
+            .filter_map(|(status, response)| match status {
+                StatusCode::Code(code) if (200..300).contains(code) => Some((*code, response)),
+                StatusCode::Range(2) => Some((200, response)),
+                _ => None,
+            })
+            .min_by_key(|(status, _)| *status)
+            .or_else(|| {
+                item.responses
+                    .default
+                    .as_ref()
+                    .map(|response| (200, response))
+            });
+        let (status, response) = match response {
+            Some((status, ReferenceOr::Reference { reference, .. })) => {
+                let reference = reference.split('/').last().unwrap();
+                match components.responses.get(reference) {
+                    Some(ReferenceOr::Item(response)) => (status, response.clone()),
+                    _ => return err!("Unknown response reference: {reference}"),
+                }
+            }
+            Some((status, ReferenceOr::Item(response))) => (status, response.clone()),
+            None => (204, openapiv3::Response::default()),
+        };
+        let mut headers = Vec::new();
+        for (name, header) in &response.headers {
+            let value = match header {
+                ReferenceOr::Item(header) => match &header.format {
+                    ParameterSchemaOrContent::Schema(schema) => {
+                        Mock::synthesize(schema, &components, 0)
+                    }
+                    _ => Value::Null,
+                },
+                _ => Value::Null,
+            };
+            let value = match value {
+                Value::String(value) => value,
+                Value::Null => "string".to_string(),
+                value => value.to_string(),
+            };
+            let name = name.to_lowercase();
+            if HeaderName::from_str(&name).is_err() {
+                return err!("Invalid header name for mock {}: {name}", operation.id);
+            }
+            if HeaderValue::from_str(&value).is_err() {
+                return err!(
+                    "Invalid example for header {name} of {}: {value:?}",
+                    operation.id
+                );
+            }
+            headers.push((name, value));
+        }
+        let media = response
+            .content
+            .get("application/json")
+            .map(|media| ("application/json".to_string(), media))
+            .or_else(|| {
+                response
+                    .content
+                    .iter()
+                    .next()
+                    .map(|(media_type, media)| (media_type.clone(), media))
+            });
+        let (content_type, body) = match media {
+            None => (None, String::new()),
+            Some((media_type, media)) => {
+                if HeaderValue::from_str(&media_type).is_err() {
+                    return err!(
+                        "Invalid media type for mock {}: {media_type:?}",
+                        operation.id
+                    );
+                }
+                let value = Mock::example(media, &components);
+                let body = if media_type.contains("json") {
+                    value.to_string()
+                } else {
+                    match value {
+                        Value::String(value) => value,
+                        Value::Null => String::new(),
+                        value => value.to_string(),
+                    }
+                };
+                (Some(media_type), body)
+            }
+        };
+        Mock::add(Mock {
+            operation: operation.id.clone(),
+            status,
+            content_type,
+            headers,
+            body,
+        });
+        Ok(())
+    }
+
+    fn example(media: &MediaType, components: &Components) -> Value {
+        if let Some(example) = media.example.as_ref() {
+            return example.clone();
+        }
+        if let Some(ReferenceOr::Item(example)) = media.examples.values().next() {
+            if let Some(value) = example.value.as_ref() {
+                return value.clone();
+            }
+        }
+        match media.schema.as_ref() {
+            Some(schema) => Mock::synthesize(schema, components, 0),
+            None => Value::Null,
+        }
+    }
+
+    fn synthesize<T: Borrow<Schema>>(
+        schema: &ReferenceOr<T>,
+        components: &Components,
+        depth: usize,
+    ) -> Value {
+        if depth > DEPTH {
+            return Value::Null;
+        }
+        let schema = match schema {
+            ReferenceOr::Reference { reference, .. } => {
+                let name = reference.split('/').last().unwrap();
+                match components.schemas.get(name) {
+                    Some(ReferenceOr::Item(schema)) if Model::type_name(name) == "DateTime" => {
+                        return match schema.schema_data.example.as_ref() {
+                            Some(example) => example.clone(),
+                            None => json!("1970-01-01T00:00:00Z"),
+                        };
+                    }
+                    Some(schema) => return Mock::synthesize(schema, components, depth),
+                    None => return Value::Null,
+                }
+            }
+            ReferenceOr::Item(schema) => schema.borrow(),
+        };
+        if let Some(example) = schema.schema_data.example.as_ref() {
+            return example.clone();
+        }
+        if let Some(default) = schema.schema_data.default.as_ref() {
+            return default.clone();
+        }
+        let object = |properties: &indexmap::IndexMap<String, ReferenceOr<Box<Schema>>>,
+                      required: &[String]| {
+            let mut object = Map::new();
+            for (name, property) in properties.iter() {
+                if depth == 0 || required.contains(name) {
+                    object.insert(
+                        name.clone(),
+                        Mock::synthesize(property, components, depth + 1),
+                    );
+                }
+            }
+            Value::Object(object)
+        };
+        match &schema.schema_kind {
+            SchemaKind::Type(Type::String(string)) => {
+                match string.enumeration.iter().flatten().next() {
+                    Some(value) => json!(value),
+                    None => match &string.format {
+                        VariantOrUnknownOrEmpty::Item(StringFormat::DateTime) => {
+                            json!("1970-01-01T00:00:00Z")
+                        }
+                        VariantOrUnknownOrEmpty::Item(StringFormat::Date) => json!("1970-01-01"),
+                        _ => json!("string"),
+                    },
+                }
+            }
+            SchemaKind::Type(Type::Integer(integer)) => {
+                match integer.enumeration.iter().flatten().next() {
+                    Some(value) => json!(value),
+                    None => json!(integer.minimum.unwrap_or(0)),
+                }
+            }
+            SchemaKind::Type(Type::Number(number)) => {
+                match number.enumeration.iter().flatten().next() {
+                    Some(value) => json!(value),
+                    None => json!(number.minimum.unwrap_or(0.0)),
+                }
+            }
+            SchemaKind::Type(Type::Boolean {}) => json!(false),
+            SchemaKind::Type(Type::Object(schema)) => object(&schema.properties, &schema.required),
+            SchemaKind::Any(schema) => object(&schema.properties, &schema.required),
+            SchemaKind::Type(Type::Array(array)) => match array.items.as_ref() {
+                Some(items) if depth < DEPTH => {
+                    json!([Mock::synthesize(items, components, depth + 1)])
+                }
+                _ => json!([]),
+            },
+            SchemaKind::OneOf { one_of } | SchemaKind::AnyOf { any_of: one_of } => {
+                match one_of.first() {
+                    Some(schema) => Mock::synthesize(schema, components, depth + 1),
+                    None => Value::Null,
+                }
+            }
+            SchemaKind::AllOf { all_of } => {
+                let mut object = Map::new();
+                for schema in all_of.iter() {
+                    if let Value::Object(fields) = Mock::synthesize(schema, components, depth + 1) {
+                        object.extend(fields);
+                    }
+                }
+                Value::Object(object)
+            }
+            _ => Value::Null,
+        }
+    }
+
+    fn validate_tokens(operation: &Operation) -> Tokens {
+        quote!(
+            $(for parameter in &operation.parameters =>
+                path_parameter::<$(&parameter.ty)>(&request.parameters, $(quoted(&parameter.original_name)))?;
+            )
+            $(for parameter in &operation.query =>
+                $(if parameter.required {
+                    if query_parameter::<$(&parameter.ty)>(&request.query, $(quoted(&parameter.original_name)))?.is_none() {
+                        return Err($(quoted(format!("missing query parameter {}", parameter.original_name))).to_string());
+                    }
+                } else {
+                    query_parameter::<$(&parameter.ty)>(&request.query, $(quoted(&parameter.original_name)))?;
+                })
+            )
+            $(if operation.request.iter().any(|content| matches!(content, Content::Json(_) | Content::Form(_))) {
+                if request.body.is_empty() {
+                    $(if operation.request_required {
+                        return Err("missing request body".to_string());
+                    })
+                } else {
+                    let content_type = request
+                        .headers
+                        .get(CONTENT_TYPE)
+                        .and_then(|value| value.to_str().ok())
+                        .unwrap_or_default();
+                    $(for content in &operation.request =>
+                        $(match content {
+                            Content::Json(ty) => {
+                                if content_type.contains("json") {
+                                    decode::<$ty>(&request.body).map_err(|error| error.to_string())?;
+                                }
+                            }
+                            Content::Form(ty) => {
+                                if content_type.contains("x-www-form-urlencoded") {
+                                    serde_urlencoded::from_bytes::<$ty>(&request.body).map_err(|error| error.to_string())?;
+                                }
+                            }
+                            _ => {}
+                        })
+                    )
+                }
+            })
+        )
+    }
+
+    fn cfg(&self) -> Tokens {
+        Operation::all()
+            .iter()
+            .find(|operation| operation.id == self.operation)
+            .map(|operation| operation.cfg())
+            .unwrap_or_default()
+    }
+
+    fn constant(&self) -> String {
+        format!("{}_example", self.operation).to_shouty_snake_case()
+    }
+
+    fn response_tokens(&self) -> Tokens {
+        quote!(
+            MockResponse::new(StatusCode::from_u16($(self.status.to_string())).unwrap())
+            $(if let Some(content_type) = self.content_type.as_ref() {
+                .header(CONTENT_TYPE, HeaderValue::from_static($(quoted(content_type))))
+            })
+            $(for (name, value) in &self.headers {
+                .header(
+                    HeaderName::from_static($(quoted(name))),
+                    HeaderValue::from_static($(quoted(value))),
+                )
+            })
+            $(if !self.body.is_empty() {
+                .body($(self.constant()))
+            })
+        )
+    }
+
+    pub fn tokens() -> Tokens {
+        let operations = Operation::all();
+        quote!(
+            use super::*;
+            use hyper::{
+                body::Bytes,
+                header::{HeaderName, HeaderValue, CONTENT_TYPE},
+                service::{make_service_fn, service_fn},
+                HeaderMap, Method, StatusCode,
+            };
+            use serde::{de::DeserializeOwned, Serialize};
+            use serde_json::Value;
+            use std::{
+                collections::HashMap,
+                convert::Infallible,
+                net::SocketAddr,
+                sync::{Arc, Mutex},
+            };
+            $['\n']
+            #[doc = "A request received by `MockServer`, matched to its operation"]
+            #[derive(Debug, Clone)]
+            pub struct MockRequest {
+                pub operation: &'static str,
+                pub method: Method,
+                pub path: String,
+                pub parameters: HashMap<String, String>,
+                pub query: Vec<(String, String)>,
+                pub headers: HeaderMap,
+                pub body: Bytes,
+            }
+            $['\n']
+            impl MockRequest {
+                pub fn json<T: DeserializeOwned>(&self) -> Result<T, Error> {
+                    decode(&self.body)
+                }
+            }
+            $['\n']
+            #[doc = "A response served by `MockServer`"]
+            #[derive(Debug, Clone)]
+            pub struct MockResponse {
+                pub status: StatusCode,
+                pub headers: HeaderMap,
+                pub body: Bytes,
+            }
+            $['\n']
+            impl MockResponse {
+                pub fn new(status: StatusCode) -> Self {
+                    Self {
+                        status,
+                        headers: HeaderMap::new(),
+                        body: Bytes::new(),
+                    }
+                }
+                $['\n']
+                pub fn json<T: Serialize>(status: StatusCode, body: &T) -> Self {
+                    Self::new(status)
+                        .header(CONTENT_TYPE, HeaderValue::from_static("application/json"))
+                        .body(serde_json::to_vec(body).expect("could not serialize mock response"))
+                }
+                $['\n']
+                pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+                    self.headers.insert(name, value);
+                    self
+                }
+                $['\n']
+                pub fn body<B: Into<Bytes>>(mut self, body: B) -> Self {
+                    self.body = body.into();
+                    self
+                }
+                $['\n']
+                fn error(status: StatusCode, message: String) -> Self {
+                    Self::json(status, &serde_json::json!({ "error": message }))
+                }
+            }
+            $['\n']
+            impl From<MockResponse> for hyper::Response<hyper::Body> {
+                fn from(mock: MockResponse) -> Self {
+                    let mut response = hyper::Response::new(hyper::Body::from(mock.body));
+                    *response.status_mut() = mock.status;
+                    *response.headers_mut() = mock.headers;
+                    response
+                }
+            }
+            $['\n']
+            type Handler = Arc<dyn Fn(&MockRequest) -> MockResponse + Send + Sync>;
+            $['\n']
+            #[derive(Default)]
+            struct MockState {
+                stubs: HashMap<String, Handler>,
+                requests: Vec<MockRequest>,
+            }
+            $['\n']
+            #[doc = "Serves every operation on localhost, answering with the spec's examples or stubs"]
+            #[doc = ""]
+            #[doc = "Path and query parameters and JSON or form bodies must decode into the generated types, or the request is answered with 400. Headers, other media types and schema constraints such as patterns or ranges are not checked."]
+            pub struct MockServer {
+                address: SocketAddr,
+                state: Arc<Mutex<MockState>>,
+                task: tokio::task::JoinHandle<()>,
+            }
+            $['\n']
+            impl MockServer {
+                pub async fn start() -> Result<Self, Error> {
+                    let state = Arc::new(Mutex::new(MockState::default()));
+                    let shared = state.clone();
+                    let service = make_service_fn(move |_| {
+                        let state = shared.clone();
+                        async move {
+                            Ok::<_, Infallible>(service_fn(move |request| {
+                                let state = state.clone();
+                                async move { Ok::<_, Infallible>(MockServer::handle(&state, request).await) }
+                            }))
+                        }
+                    });
+                    let server = hyper::Server::try_bind(&SocketAddr::from(([127, 0, 0, 1], 0)))?.serve(service);
+                    let address = server.local_addr();
+                    let task = tokio::spawn(async move {
+                        let _ = server.await;
+                    });
+                    Ok(Self { address, state, task })
+                }
+                $['\n']
+                #[doc = "Base URL to pass to `ApiBuilder::endpoint`"]
+                pub fn url(&self) -> String {
+                    format!("http://{}", self.address)
+                }
+                $['\n']
+                #[doc = "Answers an operation, by its operationId, with `handler` instead of the spec's example"]
+                pub fn stub<F: Fn(&MockRequest) -> MockResponse + Send + Sync + 'static>(&self, operation: &str, handler: F) {
+                    self.state.lock().unwrap().stubs.insert(operation.to_string(), Arc::new(handler));
+                }
+                $['\n']
+                #[doc = "Every request received so far, including rejected ones"]
+                pub fn requests(&self) -> Vec<MockRequest> {
+                    self.state.lock().unwrap().requests.clone()
+                }
+                $['\n']
+                pub fn reset(&self) {
+                    let mut state = self.state.lock().unwrap();
+                    state.stubs.clear();
+                    state.requests.clear();
+                }
+                $['\n']
+                async fn handle(state: &Mutex<MockState>, request: hyper::Request<hyper::Body>) -> hyper::Response<hyper::Body> {
+                    let (parts, body) = request.into_parts();
+                    let path = parts.uri.path().to_string();
+                    let (operation, parameters) = match route(&parts.method, &path) {
+                        Some(route) => route,
+                        None => {
+                            return MockResponse::error(StatusCode::NOT_FOUND, format!("no operation for {} {path}", parts.method)).into();
+                        }
+                    };
+                    let body = match hyper::body::to_bytes(body).await {
+                        Ok(body) => body,
+                        Err(error) => return MockResponse::error(StatusCode::BAD_REQUEST, error.to_string()).into(),
+                    };
+                    let query = match serde_urlencoded::from_str(parts.uri.query().unwrap_or_default()) {
+                        Ok(query) => query,
+                        Err(error) => return MockResponse::error(StatusCode::BAD_REQUEST, error.to_string()).into(),
+                    };
+                    let request = MockRequest {
+                        operation,
+                        method: parts.method,
+                        path,
+                        parameters,
+                        query,
+                        headers: parts.headers,
+                        body,
+                    };
+                    let response = match validate(&request) {
+                        Err(message) => MockResponse::error(StatusCode::BAD_REQUEST, message),
+                        Ok(()) => {
+                            let stub = state.lock().unwrap().stubs.get(operation).cloned();
+                            match stub {
+                                Some(handler) => handler(&request),
+                                None => example(operation),
+                            }
+                        }
+                    };
+                    state.lock().unwrap().requests.push(request);
+                    response.into()
+                }
+            }
+            $['\n']
+            impl Drop for MockServer {
+                fn drop(&mut self) {
+                    self.task.abort();
+                }
+            }
+            $['\n']
+            $(Operation::route_tokens())
+            $['\n']
+            fn validate(request: &MockRequest) -> Result<(), String> {
+                match request.operation {
+                    $(for operation in &operations =>
+                        $(operation.cfg())
+                        $(quoted(&operation.id)) => {
+                            $(Mock::validate_tokens(operation))
+                        }
+                    )
+                    _ => {}
+                }
+                Ok(())
+            }
+            $['\n']
+            $(for mock in Mock::all().iter().filter(|mock| !mock.body.is_empty()) =>
+                $(mock.cfg())
+                const $(mock.constant()): &str = $(quoted(&mock.body));
+            )
+            $['\n']
+            fn example(operation: &str) -> MockResponse {
+                match operation {
+                    $(for mock in Mock::all() =>
+                        $(mock.cfg())
+                        $(quoted(&mock.operation)) => $(mock.response_tokens()),
+                    )
+                    _ => MockResponse::new(StatusCode::NO_CONTENT),
+                }
+            }
+        )
+    }
+}
@@ -1,7 +1,10 @@
 use crate::prelude::*;
 use once_cell::sync::OnceCell;
 use openapiv3::{ReferenceOr, Schema, SchemaKind, Type};
-use std::{collections::BTreeMap, sync::Mutex};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Mutex,
+};
 
 static MODELS: OnceCell<Mutex<BTreeMap<String, Model>>> = OnceCell::new();
 
@@ -13,6 +16,7 @@ pub struct Model {
     pub description: Option<String>,
     pub properties: Vec<Property>,
     pub enumeration: Option<Enumeration>,
+    pub features: BTreeSet<String>,
 }
 
 impl Model {
@@ -37,7 +41,7 @@ impl Model {
         models.insert(model.name.clone(), model);
     }
 
-    fn get(name: &str) -> Option<Model> {
+    pub fn get(name: &str) -> Option<Model> {
         MODELS
             .get_or_init(|| Mutex::new(BTreeMap::new()))
             .lock()
@@ -46,6 +50,108 @@ impl Model {
             .cloned()
     }
 
+    pub fn type_name(name: &str) -> String {
+        let name = if name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            name.to_string()
+        } else {
+            name.to_upper_camel_case()
+        };
+        if RESERVED.contains(&name.as_str()) {
+            format!("{name}Model")
+        } else {
+            name
+        }
+    }
+
+    pub fn reference(reference: &str) -> String {
+        Model::type_name(reference.split('/').last().unwrap())
+    }
+
+    pub fn module(&self) -> String {
+        if KEYWORDS.contains(&self.path.as_str()) {
+            format!("{}_model", self.path)
+        } else {
+            self.path.clone()
+        }
+    }
+
+    pub fn dependencies(&self) -> Result<BTreeSet<String>, Error> {
+        let mut dependencies = identifiers(&self.tokens()?)?;
+        dependencies.remove(&self.name);
+        Ok(dependencies)
+    }
+
+    pub fn graph() -> Result<BTreeMap<String, BTreeSet<String>>, Error> {
+        let mut graph = BTreeMap::new();
+        for model in Model::all() {
+            graph.insert(model.name.clone(), model.dependencies()?);
+        }
+        Ok(graph)
+    }
+
+    pub fn reachability(
+        graph: &BTreeMap<String, BTreeSet<String>>,
+        fixed: &BTreeMap<String, Option<String>>,
+        roots: Vec<(Option<String>, BTreeSet<String>)>,
+    ) -> BTreeMap<String, BTreeSet<Option<String>>> {
+        fn walk(
+            graph: &BTreeMap<String, BTreeSet<String>>,
+            fixed: &BTreeMap<String, Option<String>>,
+            reached: &mut BTreeMap<String, BTreeSet<Option<String>>>,
+            module: Option<String>,
+            dependencies: BTreeSet<String>,
+        ) {
+            let mut visited = BTreeSet::new();
+            let mut stack = dependencies.into_iter().collect::<Vec<_>>();
+            while let Some(name) = stack.pop() {
+                if fixed.contains_key(&name) || !graph.contains_key(&name) {
+                    continue;
+                }
+                if !visited.insert(name.clone()) {
+                    continue;
+                }
+                reached
+                    .entry(name.clone())
+                    .or_default()
+                    .insert(module.clone());
+                stack.extend(graph[&name].iter().cloned());
+            }
+        }
+        let mut reached = BTreeMap::new();
+        for (module, dependencies) in roots {
+            walk(graph, fixed, &mut reached, module, dependencies);
+        }
+        for name in graph.keys() {
+            if !fixed.contains_key(name) && !reached.contains_key(name) {
+                let dependencies = BTreeSet::from([name.clone()]);
+                walk(graph, fixed, &mut reached, None, dependencies);
+            }
+        }
+        reached
+    }
+
+    pub fn gate() -> Result<(), Error> {
+        let graph = Model::graph()?;
+        let mut roots = Vec::new();
+        for operation in Operation::all() {
+            roots.push((operation.feature(), operation.dependencies()?));
+        }
+        let reached = Model::reachability(&graph, &BTreeMap::new(), roots);
+        let mut models = MODELS
+            .get_or_init(|| Mutex::new(BTreeMap::new()))
+            .lock()
+            .unwrap();
+        for (name, features) in reached {
+            if let Some(model) = models.get_mut(&name) {
+                model.features = features
+                    .into_iter()
+                    .collect::<Option<_>>()
+                    .unwrap_or_default();
+            }
+        }
+        Ok(())
+    }
+
     pub fn discover(name: &str, schema: &Schema) -> Result<(), Error> {
         let path = name.to_snake_case();
         let mut model = Self {
@@ -55,6 +161,7 @@ impl Model {
             description: None,
             properties: Vec::new(),
             enumeration: None,
+            features: BTreeSet::new(),
         };
         model.description = schema.schema_data.description.clone();
         match &schema.schema_kind {
@@ -90,8 +197,7 @@ impl Model {
                 for schema in all_of.iter() {
                     match schema {
                         ReferenceOr::Reference { reference, .. } => {
-                            let reference = reference.split('/').last().unwrap();
-                            let reference = Model::get(&reference).unwrap();
+                            let reference = Model::get(&Model::reference(reference)).unwrap();
                             model.properties.extend(reference.properties.clone());
                         }
                         ReferenceOr::Item(item) => match &item.schema_kind {
@@ -115,9 +221,7 @@ impl Model {
                                 for schema in one_of.iter() {
                                     match schema {
                                         ReferenceOr::Reference { reference, .. } => {
-                                            types.push(
-                                                reference.split('/').last().unwrap().to_string(),
-                                            );
+                                            types.push(Model::reference(reference));
                                         }
                                         ReferenceOr::Item(item) => {
                                             let ty = format!("{name}_{}", GREEK[g])
@@ -143,7 +247,7 @@ impl Model {
                 for schema in one_of.iter() {
                     match schema {
                         ReferenceOr::Reference { reference, .. } => {
-                            types.push(reference.split('/').last().unwrap().to_string());
+                            types.push(Model::reference(reference));
                         }
                         ReferenceOr::Item(item) => {
                             let ty = format!("{name}_{}", GREEK[g]).to_upper_camel_case();
@@ -164,6 +268,7 @@ impl Model {
     pub fn tokens(&self) -> Result<Tokens, Error> {
         let import_serialize = rust::import("serde", "Serialize");
         let import_deserialize = rust::import("serde", "Deserialize");
+        let cfg = cfg(&self.features);
         let mut tokens = Tokens::new();
         if let Some(description) = &self.description {
             tokens.append(quote!(
@@ -172,6 +277,7 @@ impl Model {
         }
         tokens.append(match &self.ty {
             Some(ty) => quote!(
+                $(&cfg)
                 pub type $(&self.name) = $ty;
             ),
             None =>  {
@@ -188,6 +294,7 @@ impl Model {
                                     }
                                 }).collect::<Vec<_>>();
                                 quote!(
+                                    $(&cfg)
                                     #[derive(Debug, Clone, PartialEq, $import_serialize, $import_deserialize)]
                                     pub enum $(&self.name) {
                                         $(for v in range =>
@@ -196,6 +303,7 @@ impl Model {
                                         )
                                     }
                                     $['\n']
+                                    $(&cfg)
                                     impl Default for $(&self.name) {
                                         fn default() -> Self { Self::$(&variants[0]) }
                                     }
@@ -203,6 +311,7 @@ impl Model {
                             }
                             Enumeration::Integer(values) => {
                                 quote!(
+                                    $(&cfg)
                                     #[derive(Debug, Clone, PartialEq, $import_serialize, $import_deserialize)]
                                     pub enum $(&self.name) {
                                         $(for value in values =>
@@ -213,6 +322,7 @@ impl Model {
                             }
                             Enumeration::Object(types) => {
                                 quote!(
+                                    $(&cfg)
                                     #[derive(Debug, Clone, PartialEq, $import_serialize, $import_deserialize)]
                                     pub enum $(&self.name) {
                                         $(for t in types =>
@@ -229,6 +339,7 @@ impl Model {
                             p.ty == strtok
                         });
                         quote!(
+                            $(&cfg)
                             #[derive(Debug, Clone, PartialEq, Default, $import_serialize, $import_deserialize)]
                             pub struct $(&self.name) {
                                 $(for property in &self.properties =>
@@ -242,6 +353,7 @@ impl Model {
                                 )
                             }
                             $['\n']
+                            $(&cfg)
                             impl $(&self.name) {
                                 pub fn new
                                 $(if has_string {<S: AsRef<str>>})
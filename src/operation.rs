@@ -2,21 +2,31 @@ use crate::prelude::*;
 use heck::{ToSnakeCase, ToUpperCamelCase};
 use hyper::Method;
 use once_cell::sync::OnceCell;
-use openapiv3::{PathItem, ReferenceOr, StatusCode::Code};
-use std::{collections::BTreeMap, sync::Mutex};
+use openapiv3::{PathItem, ReferenceOr};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Mutex,
+};
 
 static OPERATIONS: OnceCell<Mutex<BTreeMap<String, Operation>>> = OnceCell::new();
 
 #[derive(Clone)]
 pub struct Operation {
+    pub id: String,
     pub name: String,
+    pub tag: Option<String>,
     pub path: String,
     pub method: Method,
     pub description: String,
     pub parameters: Vec<Parameter>,
     pub query: Vec<Parameter>,
-    pub request: Option<Tokens>,
-    pub response: Option<Tokens>,
+    pub request: Vec<Content>,
+    pub request_required: bool,
+    pub responses: Vec<Response>,
+    pub security: Vec<Vec<String>>,
+    pub server: Option<String>,
+    pub retryable: bool,
+    pub pagination: Option<Pagination>,
 }
 
 impl Operation {
@@ -36,7 +46,7 @@ impl Operation {
             .lock()
             .unwrap();
         if operations.contains_key(&operation.name) {
-            panic!("Operation {} already exists", operation.name);
+            return err!("Operation {} already exists", operation.name);
         }
         operations.insert(operation.name.clone(), operation);
         Ok(())
@@ -58,41 +68,89 @@ impl Operation {
             }
             ReferenceOr::Item(item) => {
                 if let Some(op) = &item.get {
-                    Self::discover(path, Method::GET, op.clone())?;
+                    Self::discover(path, Method::GET, op.clone(), &item.servers)?;
                 }
                 if let Some(op) = &item.put {
-                    Self::discover(path, Method::PUT, op.clone())?;
+                    Self::discover(path, Method::PUT, op.clone(), &item.servers)?;
                 }
                 if let Some(op) = &item.post {
-                    Self::discover(path, Method::POST, op.clone())?;
+                    Self::discover(path, Method::POST, op.clone(), &item.servers)?;
                 }
                 if let Some(op) = &item.delete {
-                    Self::discover(path, Method::DELETE, op.clone())?;
+                    Self::discover(path, Method::DELETE, op.clone(), &item.servers)?;
                 }
                 if let Some(op) = &item.options {
-                    Self::discover(path, Method::OPTIONS, op.clone())?;
+                    Self::discover(path, Method::OPTIONS, op.clone(), &item.servers)?;
                 }
                 if let Some(op) = &item.head {
-                    Self::discover(path, Method::HEAD, op.clone())?;
+                    Self::discover(path, Method::HEAD, op.clone(), &item.servers)?;
                 }
                 if let Some(op) = &item.patch {
-                    Self::discover(path, Method::PATCH, op.clone())?;
+                    Self::discover(path, Method::PATCH, op.clone(), &item.servers)?;
                 }
                 if let Some(op) = &item.trace {
-                    Self::discover(path, Method::TRACE, op.clone())?;
+                    Self::discover(path, Method::TRACE, op.clone(), &item.servers)?;
                 }
             }
         }
         Ok(())
     }
 
-    pub fn discover(path: &str, method: Method, schema: openapiv3::Operation) -> Result<(), Error> {
-        let name = match schema.operation_id {
-            Some(name) => name.to_snake_case(),
-            None => {
-                return err!("Operation is missing operationId: {}", path);
+    fn synthesize_name(path: &str, method: &Method) -> String {
+        let mut name = method.as_str().to_lowercase();
+        for segment in path.split('/').filter(|segment| !segment.is_empty()) {
+            if segment.len() > 1
+                && segment.starts_with('v')
+                && segment[1..].chars().all(|c| c.is_ascii_digit())
+            {
+                continue;
+            }
+            match segment
+                .strip_prefix('{')
+                .and_then(|segment| segment.strip_suffix('}'))
+            {
+                Some(parameter) => name.push_str(&format!("_by_{}", parameter.to_snake_case())),
+                None => name.push_str(&format!("_{}", segment.to_snake_case())),
             }
+        }
+        name
+    }
+
+    pub fn discover(
+        path: &str,
+        method: Method,
+        schema: openapiv3::Operation,
+        servers: &[openapiv3::Server],
+    ) -> Result<(), Error> {
+        let renames = &Config::get().rename;
+        let name = match renames
+            .get(&format!("{method} {path}"))
+            .or_else(|| schema.operation_id.as_ref().and_then(|id| renames.get(id)))
+        {
+            Some(name) => name.to_snake_case(),
+            None => match schema.operation_id.as_ref() {
+                Some(name) => name.to_snake_case(),
+                None => Self::synthesize_name(path, &method),
+            },
         };
+        if let Some(existing) = Operation::get(&name) {
+            return err!(
+                "Operation name '{}' for {} {} collides with {} {}, add a rename entry to the config",
+                name,
+                method,
+                path,
+                existing.method,
+                existing.path
+            );
+        }
+        let pagination = Pagination::discover(
+            &name,
+            &[
+                format!("{method} {path}"),
+                schema.operation_id.clone().unwrap_or_default(),
+            ],
+            &schema,
+        )?;
         let mut parameters = Vec::new();
         let mut query = Vec::new();
         for item in schema.parameters {
@@ -119,163 +177,726 @@ impl Operation {
                 },
             }
         }
-        let mut request = None;
+        let mut request = Vec::new();
+        let mut request_required = false;
         if let Some(item) = schema.request_body.as_ref() {
-            match item {
-                ReferenceOr::Reference { .. } => {
-                    unimplemented!();
-                }
-                ReferenceOr::Item(item) => {
-                    if item.content.get("application/json").is_none() {
-                        return err!("Request is missing application/json content type: {path}");
-                    }
-                    let schema = match item
-                        .content
-                        .get("application/json")
-                        .unwrap()
-                        .schema
-                        .as_ref()
-                        .unwrap()
-                    {
-                        ReferenceOr::Reference { reference, .. } => {
-                            return err!("References not implemented: {}", reference);
-                        }
-                        ReferenceOr::Item(item) => item,
-                    };
-                    let name = format!("{name}_request").to_upper_camel_case();
-                    let module = import("super", &name);
-                    Model::discover(&name, schema)?;
-                    request = Some(quote!($module));
-                }
-            }
+            let item = Content::request_body(item)?;
+            request = Content::discover(&name, &item.content)?;
+            request_required = item.required;
         }
-        let mut response = None;
-        for item in schema.responses.responses.iter() {
-            match item {
-                (status, item) => {
-                    if *status != Code(200) {
-                        continue;
-                    }
-                    match item {
-                        ReferenceOr::Reference { reference, .. } => {
-                            return err!("References not implemented: {}", reference);
-                        }
-                        ReferenceOr::Item(item) => {
-                            if item.content.get("application/json").is_none() {
-                                return err!(
-                                    "Response is missing application/json content type: {path}"
-                                );
-                            }
-                            let schema = match item
-                                .content
-                                .get("application/json")
-                                .unwrap()
-                                .schema
-                                .as_ref()
-                                .unwrap()
-                            {
-                                ReferenceOr::Reference { reference, .. } => {
-                                    return err!("References not implemented: {}", reference);
-                                }
-                                ReferenceOr::Item(item) => item,
-                            };
-                            let name = format!("{name}_response").to_upper_camel_case();
-                            let module = import("super", &name);
-                            Model::discover(&name, schema)?;
-                            response = Some(quote!($module));
-                        }
-                    };
-                }
+        let success = schema
+            .responses
+            .responses
+            .keys()
+            .filter(|status| Status::from(*status).is_success())
+            .count();
+        let response_name = |status: &Status| {
+            if (status.is_success() && success == 1) || (*status == Status::Default && success == 0)
+            {
+                format!("{name}_response").to_upper_camel_case()
+            } else {
+                format!("{name}_{}_response", status.variant()).to_upper_camel_case()
             }
+        };
+        let mut responses = Vec::new();
+        for (status, item) in schema.responses.responses.iter() {
+            let status = Status::from(status);
+            responses.push(Response::discover(&response_name(&status), status, item)?);
+        }
+        if let Some(item) = schema.responses.default.as_ref() {
+            let status = Status::Default;
+            responses.push(Response::discover(&response_name(&status), status, item)?);
+        }
+        let security = match schema.security.as_ref() {
+            Some(requirements) => Security::requirements(requirements),
+            None => Security::default_requirements(),
+        };
+        let servers = if schema.servers.is_empty() {
+            servers
+        } else {
+            &schema.servers
+        };
+        let mut names = Vec::new();
+        for server in servers {
+            names.push(Server::discover(server)?);
         }
+        let server = names.into_iter().next();
+        let retryable = match schema.extensions.get("x-retryable") {
+            Some(value) => match value.as_bool() {
+                Some(retryable) => retryable,
+                None => return err!("x-retryable of '{name}' must be a boolean"),
+            },
+            None => [
+                Method::GET,
+                Method::HEAD,
+                Method::PUT,
+                Method::DELETE,
+                Method::OPTIONS,
+                Method::TRACE,
+            ]
+            .contains(&method),
+        };
+        let tag = schema.tags.first().map(|tag| Tag::discover(tag, None).name);
         Operation::add(Operation {
+            id: schema.operation_id.unwrap_or_else(|| name.clone()),
             name,
+            tag,
             path: path.to_string(),
             method,
             description: schema.description.unwrap_or_default(),
             parameters,
             query,
             request,
-            response,
+            request_required,
+            responses,
+            security,
+            server,
+            retryable,
+            pagination,
         })?;
         Ok(())
     }
 
-    pub fn tokens(&self) -> Result<Tokens, Error> {
-        // let response_type = self.response.clone().unwrap_or(quote!(Value));
-        Ok(quote!(
-            #[doc = $(quoted(&self.description))]
-            pub async fn $(self.name.to_snake_case())(&self
-                $(for parameter in &self.parameters {
-                    , $(&parameter.name):
-                    $(if parameter.required {
-                        $(&parameter.ty)
-                    } else {
-                        Option<$(&parameter.ty)>
+    pub fn feature(&self) -> Option<String> {
+        Tag::get(self.tag.as_ref()?)?.feature()
+    }
+
+    pub fn cfg(&self) -> Tokens {
+        cfg(&self.feature().into_iter().collect())
+    }
+
+    fn server_tokens(&self) -> Tokens {
+        match self.server.as_ref() {
+            Some(server) => quote!(Some($(Server::value(server)))),
+            None => quote!(None),
+        }
+    }
+
+    pub fn body_type(&self) -> Tokens {
+        if self.request.len() == 1 {
+            self.request[0].ty()
+        } else {
+            quote!($(format!("{}_body", self.name).to_upper_camel_case()))
+        }
+    }
+
+    pub fn body_tokens(&self) -> Tokens {
+        if self.request.len() < 2 {
+            return Tokens::new();
+        }
+        quote!(
+            $(self.cfg())
+            pub enum $(self.body_type()) {
+                $(for content in &self.request =>
+                    $(content.variant())($(content.ty())),
+                )
+            }
+        )
+    }
+
+    fn encode(&self) -> Tokens {
+        if self.request.len() == 1 {
+            return self.request[0].encode(quote!(body));
+        }
+        let body_type = self.body_type();
+        quote!(
+            match body {
+                $(for content in &self.request =>
+                    $(&body_type)::$(content.variant())(body) => $(content.encode(quote!(body))),
+                )
+            }
+        )
+    }
+
+    pub fn is_metadata(&self) -> bool {
+        matches!(self.method, Method::HEAD | Method::OPTIONS | Method::TRACE)
+    }
+
+    pub fn is_enumeration(&self) -> bool {
+        if self.is_metadata() {
+            return false;
+        }
+        let success = Response::success(&self.responses);
+        success
+            .iter()
+            .any(|response| response.body != success[0].body)
+    }
+
+    pub fn response_type(&self) -> Tokens {
+        if self.is_metadata() {
+            return match self.headers_type() {
+                Some(headers) => quote!(ApiResponse<(), $headers>),
+                None => quote!(ApiResponse<()>),
+            };
+        }
+        if self.is_enumeration() {
+            return quote!($(format!("{}_response", self.name).to_upper_camel_case()));
+        }
+        match Response::success(&self.responses)
+            .first()
+            .and_then(|response| response.body.ty())
+        {
+            Some(ty) => ty,
+            None => quote!(()),
+        }
+    }
+
+    pub fn response_tokens(&self) -> Tokens {
+        if !self.is_enumeration() {
+            return Tokens::new();
+        }
+        let success = Response::success(&self.responses);
+        let streaming = success.iter().any(|response| response.body.is_streaming());
+        quote!(
+            $(self.cfg())
+            $(if streaming {
+                #[derive(Debug)]
+            } else {
+                #[derive(Debug, Clone, PartialEq)]
+            })
+            pub enum $(self.response_type()) {
+                $(for response in &success =>
+                    $(response.status.variant())$(response.body.ty().map(|ty| quote!(($ty)))),
+                )
+            }
+        )
+    }
+
+    pub fn headers(&self) -> Vec<Header> {
+        let success = Response::success(&self.responses);
+        let mut headers: Vec<Header> = Vec::new();
+        for response in &success {
+            for header in &response.headers {
+                if !headers.iter().any(|existing| existing.name == header.name) {
+                    let required = success.iter().all(|response| {
+                        response
+                            .headers
+                            .iter()
+                            .any(|other| other.name == header.name && other.required)
+                    });
+                    headers.push(Header {
+                        required,
+                        ..header.clone()
+                    });
+                }
+            }
+        }
+        headers
+    }
+
+    pub fn headers_type(&self) -> Option<Tokens> {
+        if self.headers().is_empty() {
+            return None;
+        }
+        Some(quote!($(format!("{}_headers", self.name).to_upper_camel_case())))
+    }
+
+    pub fn headers_tokens(&self) -> Tokens {
+        let ty = match self.headers_type() {
+            Some(ty) => ty,
+            None => return Tokens::new(),
+        };
+        let headers = self.headers();
+        quote!(
+            $(self.cfg())
+            #[derive(Debug, Clone, PartialEq, Default)]
+            pub struct $(&ty) {
+                $(for header in &headers =>
+                    $(header.description.as_ref().map(|description| quote!(#[doc = $(quoted(description))])))
+                    pub $(&header.field): $(if header.required { $(&header.ty) } else { Option<$(&header.ty)> }),
+                )
+            }
+            $['\n']
+            $(self.cfg())
+            impl TryFrom<&HeaderMap> for $(&ty) {
+                type Error = Error;
+                $['\n']
+                fn try_from(headers: &HeaderMap) -> Result<Self, Error> {
+                    Ok(Self {
+                        $(for header in &headers =>
+                            $(&header.field): header(headers, $(quoted(&header.name)))?
+                            $(if header.required {
+                                .ok_or_else(|| Error::Validation($(quoted(format!("missing {} header", header.name))).to_string()))?
+                            }),
+                        )
                     })
-                })
-                $(for parameter in &self.query {
-                    , $(&parameter.name):
-                    $(if parameter.required {
-                        $(&parameter.ty)
+                }
+            }
+        )
+    }
+
+    fn with_response_type(&self) -> Tokens {
+        if self.is_metadata() {
+            return self.response_type();
+        }
+        match self.headers_type() {
+            Some(headers) => quote!(ApiResponse<$(self.response_type()), $headers>),
+            None => quote!(ApiResponse<$(self.response_type())>),
+        }
+    }
+
+    fn decode(&self) -> Tokens {
+        let (fallback, success): (Vec<_>, Vec<_>) = Response::success(&self.responses)
+            .into_iter()
+            .partition(|response| response.status == Status::Default);
+        let enumeration = self.is_enumeration();
+        let response_type = self.response_type();
+        let mut errors: Vec<(Vec<Status>, Option<String>)> = Vec::new();
+        for response in Response::errors(&self.responses) {
+            let ty = match response.body {
+                Body::Json(ty) if !self.is_metadata() => Some(ty),
+                _ => None,
+            };
+            match errors.last_mut() {
+                Some((statuses, last)) if *last == ty && response.status != Status::Default => {
+                    statuses.push(response.status)
+                }
+                _ => errors.push((vec![response.status], ty)),
+            }
+        }
+        let has_default = self
+            .responses
+            .iter()
+            .any(|response| response.status == Status::Default);
+        let metadata = match self.headers_type() {
+            Some(_) => quote!(ApiResponse::metadata(response).typed()),
+            None => quote!(Ok(ApiResponse::metadata(response))),
+        };
+        let value = |response: &Response| {
+            if enumeration {
+                quote!(
+                    $(&response_type)::$(response.status.variant())
+                    $(response.body.decode().map(|decode| quote!(($decode))))
+                )
+            } else {
+                response.body.decode().unwrap_or(quote!(()))
+            }
+        };
+        let arm = |response: &Response| {
+            if self.is_metadata() {
+                metadata.clone()
+            } else {
+                quote!(Ok($(value(response))))
+            }
+        };
+        quote!(
+            match response.status().as_u16() {
+                $(for response in &success =>
+                    $(response.status.pattern()) => $(arm(response)),
+                )
+                $(if success.is_empty() && fallback.is_empty() {
+                    200..=299 => $(if self.is_metadata() {
+                        $(&metadata)
                     } else {
-                        Option<$(&parameter.ty)>
-                    })
+                        Ok(())
+                    }),
                 })
-                $(if self.request.is_some() {
-                    , body: $(self.request.as_ref().unwrap())
-                })
-            ) -> Result<$(self.response.as_ref().unwrap_or(&quote!(()))), Error> {
-                let
-                $(if !self.parameters.is_empty() || !self.query.is_empty() {
-                   mut
-                })
-                path = String::from($(quoted(&self.path)));
-                $(for parameter in &self.parameters {
-                    path = path.replace($(quoted(quote!({$(&parameter.original_name)}))), &$(&parameter.name));
-                })
-                $(if !self.query.is_empty() {
-                    let mut query = Value::Object(serde_json::Map::new());
-                    $(for parameter in &self.query {
-                        $(if parameter.required {
-                            resolve(&mut query, $(quoted(&parameter.original_name)), Some($(&parameter.name)));
-                        } else {
-                            resolve(&mut query, $(quoted(&parameter.original_name)), $(&parameter.name));
+                $(for (statuses, ty) in &errors =>
+                    $(for status in statuses join ( | ) => $(status.pattern())) => Err(
+                        $(match ty {
+                            Some(ty) => { ApiError::$ty(ApiResponse::decode(response).await?).into() },
+                            None => { Error::Status { code: response.status(), body: bytes(response).await? } },
                         })
-                    })
-                    let query = serde_urlencoded::to_string(&query)?;
-                    if !query.is_empty() {
-                        path = format!("{path}?{query}");
-                    }
-                })
-                $(if self.response.is_some() {
-                    let response =
+                    ),
+                )
+                $(for response in &fallback =>
+                    $(response.status.pattern()) => $(arm(response)),
+                )
+                $(if !has_default {
+                    _ => Err(Error::Status { code: response.status(), body: bytes(response).await? }),
                 })
-                $(match self.method {
-                    Method::GET => {
-                        self.request::<_, Value, $(if self.response.is_some() { _ } else { Value })>(Method::GET, path, None).await?;
-                    },
-                    Method::PUT => {
-                        self.request::<_, _, $(if self.response.is_some() { _ } else { Value })>(Method::PUT, path, Some(body)).await?;
+            }
+        )
+    }
+
+    pub fn route_tokens() -> Tokens {
+        let operations = Operation::all();
+        quote!(
+            const ROUTES: [(&str, &str, &str); $(operations.len().to_string())] = [
+                $(for operation in &operations =>
+                    ($(quoted(operation.method.as_str())), $(quoted(&operation.path)), $(quoted(&operation.id))),
+                )
+            ];
+            $['\n']
+            fn route(method: &Method, path: &str) -> Option<(&'static str, HashMap<String, String>)> {
+                let segments = path.trim_matches('/').split('/').collect::<Vec<_>>();
+                let mut best: Option<(usize, &'static str, HashMap<String, String>)> = None;
+                for (route_method, template, operation) in ROUTES {
+                    let parts = template.trim_matches('/').split('/').collect::<Vec<_>>();
+                    if route_method != method.as_str() || parts.len() != segments.len() {
+                        continue;
                     }
-                    Method::POST => {
-                        self.request::<_, _, $(if self.response.is_some() { _ } else { Value })>(Method::POST, path, Some(body)).await?;
+                    let mut literals = 0;
+                    let mut parameters = HashMap::new();
+                    let matched = parts.iter().zip(&segments).all(|(part, segment)| {
+                        match part.strip_prefix('{').and_then(|part| part.strip_suffix('}')) {
+                            Some(name) => match percent_decode(segment) {
+                                Some(value) if !value.is_empty() => {
+                                    parameters.insert(name.to_string(), value);
+                                    true
+                                }
+                                _ => false,
+                            },
+                            None => {
+                                literals += 1;
+                                part == segment
+                            }
+                        }
+                    });
+                    if matched && best.as_ref().map_or(true, |(best, _, _)| literals > *best) {
+                        best = Some((literals, operation, parameters));
                     }
-                    Method::DELETE => {
-                        self.request::<_, Value, $(if self.response.is_some() { _ } else { Value })>(Method::DELETE, path, None).await?;
+                }
+                best.map(|(_, operation, parameters)| (operation, parameters))
+            }
+            $['\n']
+            fn percent_decode(segment: &str) -> Option<String> {
+                let bytes = segment.as_bytes();
+                let mut decoded = Vec::with_capacity(bytes.len());
+                let mut index = 0;
+                while index < bytes.len() {
+                    if bytes[index] == b'%' {
+                        let hex = segment
+                            .get(index + 1..index + 3)
+                            .filter(|hex| hex.bytes().all(|byte| byte.is_ascii_hexdigit()))?;
+                        decoded.push(u8::from_str_radix(hex, 16).ok()?);
+                        index += 3;
+                    } else {
+                        decoded.push(bytes[index]);
+                        index += 1;
                     }
-                    Method::PATCH => {
-                        self.request::<_, _, $(if self.response.is_some() { _ } else { Value })>(Method::PATCH, path, Some(body)).await?;
+                }
+                String::from_utf8(decoded).ok()
+            }
+            $['\n']
+            fn path_parameter<T: DeserializeOwned>(parameters: &HashMap<String, String>, name: &str) -> Result<T, String> {
+                let value = parameters.get(name).cloned().unwrap_or_default();
+                serde_json::from_value(Value::String(value.clone()))
+                    .or_else(|_| serde_json::from_str(&value))
+                    .map_err(|_| format!("invalid path parameter {name}"))
+            }
+            $['\n']
+            fn query_parameter<T: DeserializeOwned>(query: &[(String, String)], name: &str) -> Result<Option<T>, String> {
+                let mut values = Vec::new();
+                let mut fields = serde_json::Map::new();
+                for (key, value) in query {
+                    if key == name || key.strip_prefix(name) == Some("[]") {
+                        values.push(Value::String(value.clone()));
+                    } else if let Some(field) = key
+                        .strip_prefix(name)
+                        .and_then(|key| key.strip_prefix('['))
+                        .and_then(|key| key.strip_suffix(']'))
+                    {
+                        fields.insert(field.to_string(), Value::String(value.clone()));
                     }
-                    _ => {
-                        None;
-                    },
+                }
+                let strings = if !fields.is_empty() {
+                    Value::Object(fields)
+                } else if values.len() > 1 {
+                    Value::Array(values)
+                } else {
+                    match values.pop() {
+                        Some(value) => value,
+                        None => return Ok(None),
+                    }
+                };
+                let split = match strings {
+                    Value::String(ref value) => Value::Array(
+                        value
+                            .split(',')
+                            .map(|value| Value::String(value.to_string()))
+                            .collect(),
+                    ),
+                    ref value => value.clone(),
+                };
+                for candidate in [
+                    scalars(&strings),
+                    strings,
+                    scalars(&split),
+                    split,
+                ] {
+                    if let Ok(value) = serde_json::from_value(candidate) {
+                        return Ok(Some(value));
+                    }
+                }
+                Err(format!("invalid query parameter {name}"))
+            }
+            $['\n']
+            fn scalars(value: &Value) -> Value {
+                match value {
+                    Value::String(string) => serde_json::from_str(string).unwrap_or_else(|_| value.clone()),
+                    Value::Array(items) => Value::Array(items.iter().map(scalars).collect()),
+                    Value::Object(fields) => Value::Object(
+                        fields
+                            .iter()
+                            .map(|(key, value)| (key.clone(), scalars(value)))
+                            .collect(),
+                    ),
+                    value => value.clone(),
+                }
+            }
+        )
+    }
+
+    pub fn dependencies(&self) -> Result<BTreeSet<String>, Error> {
+        let mut dependencies = identifiers(&self.tokens(&self.name, quote!(self))?)?;
+        dependencies.extend(identifiers(&self.stream_tokens(
+            &self.name,
+            quote!(self),
+            quote!('_),
+        )?)?);
+        dependencies.extend(identifiers(&self.body_tokens())?);
+        dependencies.extend(identifiers(&self.response_tokens())?);
+        dependencies.extend(identifiers(&self.headers_tokens())?);
+        Ok(dependencies)
+    }
+
+    fn arguments(&self) -> Tokens {
+        quote!(
+            $(for parameter in &self.parameters {
+                , $(&parameter.name):
+                $(if parameter.required {
+                    $(&parameter.ty)
+                } else {
+                    Option<$(&parameter.ty)>
+                })
+            })
+            $(for parameter in &self.query {
+                , $(&parameter.name):
+                $(if parameter.required {
+                    $(&parameter.ty)
+                } else {
+                    Option<$(&parameter.ty)>
+                })
+            })
+            $(if !self.request.is_empty() {
+                , body:
+                $(if self.request_required {
+                    $(self.body_type())
+                } else {
+                    Option<$(self.body_type())>
                 })
-                $(if self.response.is_some() {
-                    Ok(response.unwrap())
+            })
+        )
+    }
+
+    pub fn blocking_tokens(&self, name: &str, api: Tokens, client: Tokens) -> Tokens {
+        let with_response = format!("{name}_with_response");
+        quote!(
+            #[doc = $(quoted(&self.description))]
+            pub fn $name(&self $(self.arguments())) -> Result<$(self.response_type()), Error> {
+                $(&api).runtime.block_on($(&api).inner$(&client).$name($(self.forward())))
+            }
+            $(if !self.is_metadata() {
+                $['\n']
+                #[doc = $(quoted(format!("Like `{name}`, also returning the status and headers of the response")))]
+                pub fn $(&with_response)(&self $(self.arguments())) -> Result<$(self.with_response_type()), Error> {
+                    $(&api).runtime.block_on($api.inner$client.$(&with_response)($(self.forward())))
+                }
+            })
+        )
+    }
+
+    fn span_tokens(&self) -> Tokens {
+        quote!(tracing::info_span!(
+            "operation",
+            operation = $(quoted(&self.id)),
+            method = $(quoted(self.method.as_str())),
+            path = $(quoted(&self.path)),
+            status = tracing::field::Empty,
+        ))
+    }
+
+    fn path_tokens(&self) -> Tokens {
+        quote!(
+            let
+            $(if !self.parameters.is_empty() || !self.query.is_empty() {
+               mut
+            })
+            path = String::from($(quoted(&self.path)));
+            $(for parameter in &self.parameters {
+                path = path.replace($(quoted(quote!({$(&parameter.original_name)}))), &encode_path(&$(&parameter.name)));
+            })
+            $(if !self.query.is_empty() {
+                let mut query = Value::Object(serde_json::Map::new());
+                $(for parameter in &self.query {
+                    $(if parameter.required {
+                        resolve(&mut query, $(quoted(&parameter.original_name)), Some($(&parameter.name)));
+                    } else {
+                        resolve(&mut query, $(quoted(&parameter.original_name)), $(&parameter.name));
+                    })
+                })
+                let query = serde_urlencoded::to_string(&query)?;
+                if !query.is_empty() {
+                    path = format!("{path}?{query}");
+                }
+            })
+        )
+    }
+
+    fn request_tokens(&self, api: &Tokens, with_response: bool) -> Tokens {
+        let method = quote!(Method::$(self.method.as_str()));
+        let payload = if self.request.is_empty() {
+            quote!(None)
+        } else if self.request_required {
+            quote!(Some($(self.encode())))
+        } else {
+            quote!(match body {
+                Some(body) => Some($(self.encode())),
+                None => None,
+            })
+        };
+        let typed_headers = match self.headers_type() {
+            Some(ty) => quote!($ty::try_from(&headers)?),
+            None => quote!(()),
+        };
+        quote!(
+            let response = $api.request($method, $(self.server_tokens()), path, $(Security::requirement_tokens(&self.security)), $(self.retryable.to_string()), $payload).await?;
+            $(if self.is_metadata() || !with_response {
+                $(self.decode())
+            } else {
+                let status = response.status();
+                let headers = response.headers().clone();
+                let body: Result<$(self.response_type()), Error> = $(self.decode());
+                let body = body?;
+                Ok(ApiResponse {
+                    status,
+                    typed_headers: $typed_headers,
+                    headers,
+                    body,
+                })
+            })
+        )
+    }
+
+    pub fn stream_tokens(
+        &self,
+        name: &str,
+        client: Tokens,
+        lifetime: Tokens,
+    ) -> Result<Tokens, Error> {
+        let pagination = match self.pagination.as_ref() {
+            Some(pagination) => pagination,
+            None => return Ok(Tokens::new()),
+        };
+        if !self.request.is_empty() {
+            return err!("Paginated '{}' must not take a request body", self.name);
+        }
+        let ty = match Response::success(&self.responses)
+            .first()
+            .map(|response| &response.body)
+        {
+            Some(Body::Json(ty)) if !self.is_enumeration() && !self.is_metadata() => ty.clone(),
+            _ => return err!("Paginated '{}' must return a single JSON model", self.name),
+        };
+        let (property, item) = pagination.item(self, &ty)?;
+        let mut pairs = vec![quote!(($(quoted(&pagination.page)), number.to_string()))];
+        if let (Some(size), Some(per_page)) = (pagination.size.as_ref(), pagination.per_page) {
+            pairs.push(quote!(($(quoted(size)), $(per_page.to_string()).to_string())));
+        }
+        let api = quote!(api);
+        let response_type = self.response_type();
+        Ok(quote!(
+            #[doc = $(quoted(format!("Every item of `{name}`, fetching page after page")))]
+            pub fn $(format!("{name}_stream"))(&self $(self.arguments())) -> impl futures_util::Stream<Item = Result<$(&item), Error>> + $lifetime {
+                let api = $client;
+                $(if self.query.is_empty() {
+                    $(self.path_tokens())
+                    let error: Option<Error> = None;
                 } else {
-                    Ok(())
+                    let (path, error) = match (move || -> Result<String, Error> {
+                        $(self.path_tokens())
+                        Ok(path)
+                    })() {
+                        Ok(path) => (path, None),
+                        Err(error) => (String::new(), Some(error)),
+                    };
                 })
+                futures_util::stream::unfold(
+                    (Some($(pagination.start.to_string())u64), Vec::<$(&item)>::new().into_iter(), None, error),
+                    move |(mut page, mut items, mut last, error): (_, _, Option<Vec<$(&item)>>, _)| {
+                        let path = path.clone();
+                        async move {
+                            if let Some(error) = error {
+                                return Some((Err(error), (None, items, last, None)));
+                            }
+                            loop {
+                                if let Some(item) = items.next() {
+                                    return Some((Ok(item), (page, items, last, None)));
+                                }
+                                let number = page?;
+                                let result: Result<$(&response_type), Error> = tracing::Instrument::instrument(async {
+                                    let path = with_query(&path, &[$(for pair in &pairs join (, ) => $pair)])?;
+                                    $(self.request_tokens(&api, false))
+                                }, $(self.span_tokens())).await;
+                                let batch = match result {
+                                    Ok(response) => response.$(&property.safe_name)
+                                        $(if !property.required || property.nullable { .unwrap_or_default() }),
+                                    Err(error) => return Some((Err(error), (None, items, last, None))),
+                                };
+                                if last.as_ref() == Some(&batch) {
+                                    return None;
+                                }
+                                page = if batch.is_empty()
+                                    || last.as_ref().map_or(false, |last| batch.len() < last.len())
+                                    $(if let Some(per_page) = pagination.per_page.filter(|_| pagination.size.is_some()) {
+                                        || batch.len() < $(per_page.to_string())
+                                    })
+                                    $(if let Some(max_pages) = pagination.max_pages {
+                                        || number >= $((pagination.start + max_pages.saturating_sub(1)).to_string())
+                                    })
+                                {
+                                    None
+                                } else {
+                                    Some(number + 1)
+                                };
+                                last = Some(batch.clone());
+                                items = batch.into_iter();
+                            }
+                        }
+                    },
+                )
+            }
+        ))
+    }
+
+    fn forward(&self) -> Tokens {
+        quote!(
+            $(for parameter in self.parameters.iter().chain(&self.query) join (, ) => $(&parameter.name))
+            $(if !self.request.is_empty() {
+                $(if !self.parameters.is_empty() || !self.query.is_empty() { , })
+                body
+            })
+        )
+    }
+
+    pub fn tokens(&self, name: &str, api: Tokens) -> Result<Tokens, Error> {
+        let with_response = if self.is_metadata() {
+            name.to_string()
+        } else {
+            format!("{name}_with_response")
+        };
+        Ok(quote!(
+            $(if !self.is_metadata() {
+                #[doc = $(quoted(&self.description))]
+                pub async fn $name(&self $(self.arguments())) -> Result<$(self.response_type()), Error> {
+                    let span = $(self.span_tokens());
+                    tracing::Instrument::instrument(async move {
+                        $(self.path_tokens())
+                        $(self.request_tokens(&api, false))
+                    }, span).await
+                }
+                $['\n']
+                #[doc = $(quoted(format!("Like `{name}`, also returning the status and headers of the response")))]
+            } else {
+                #[doc = $(quoted(&self.description))]
+            })
+            pub async fn $(&with_response)(&self $(self.arguments())) -> Result<$(self.with_response_type()), Error> {
+                let span = $(self.span_tokens());
+                tracing::Instrument::instrument(async move {
+                    $(self.path_tokens())
+                    $(self.request_tokens(&api, true))
+                }, span).await
             }
         ))
     }
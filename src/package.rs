@@ -0,0 +1,197 @@
+use crate::prelude::*;
+use openapiv3::OpenAPI;
+use serde::Deserialize;
+
+const DEPENDENCIES: [(&str, &str); 11] = [
+    ("chrono", r#"{ version = "0.4.24", features = ["serde"] }"#),
+    ("futures-util", r#""0.3.28""#),
+    (
+        "hyper",
+        r#"{ version = "0.14.26", features = ["http1", "http2", "client", "stream", "tcp"] }"#,
+    ),
+    (
+        "hyper-rustls",
+        r#"{ version = "0.24.2", features = ["http2"], optional = true }"#,
+    ),
+    ("hyper-tls", r#"{ version = "0.5.0", optional = true }"#),
+    ("serde", r#"{ version = "1.0.160", features = ["derive"] }"#),
+    ("serde_json", r#""1.0.96""#),
+    ("serde_path_to_error", r#""0.1.11""#),
+    ("serde_urlencoded", r#""0.7.1""#),
+    (
+        "tokio",
+        r#"{ version = "1.28.0", features = ["io-util", "net", "rt", "time"] }"#,
+    ),
+    (
+        "tracing",
+        r#"{ version = "0.1.37", default-features = false, features = ["std"] }"#,
+    ),
+];
+
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Package {
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub license: Option<String>,
+}
+
+impl Package {
+    pub fn name(&self, schema: &OpenAPI) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None => schema.info.title.to_kebab_case(),
+        }
+    }
+
+    pub fn version(&self, schema: &OpenAPI) -> String {
+        if let Some(version) = &self.version {
+            return version.clone();
+        }
+        let version = schema.info.version.trim_start_matches('v');
+        let parts = version.split('.').collect::<Vec<_>>();
+        if parts.iter().any(|part| part.parse::<u64>().is_err()) {
+            return "0.1.0".to_string();
+        }
+        match parts.len() {
+            1 => format!("{version}.0.0"),
+            2 => format!("{version}.0"),
+            3 => version.to_string(),
+            _ => "0.1.0".to_string(),
+        }
+    }
+
+    pub fn write(&self, schema: &OpenAPI, output: &str) -> Result<(), Error> {
+        std::fs::create_dir_all(output)?;
+        let name = self.name(schema);
+        let mut manifest = format!(
+            "[package]\nname = \"{name}\"\nversion = \"{}\"\nedition = \"2021\"\n",
+            self.version(schema)
+        );
+        if let Some(description) = schema.info.description.as_ref() {
+            let summary = description
+                .trim()
+                .lines()
+                .take_while(|line| !line.trim().is_empty())
+                .map(|line| line.trim())
+                .collect::<Vec<_>>()
+                .join(" ");
+            if !summary.is_empty() {
+                manifest.push_str(&format!("description = {summary:?}\n"));
+            }
+        }
+        let text = match self.license.as_ref() {
+            Some(path) => match std::fs::read_to_string(path) {
+                Ok(text) => Some(text),
+                Err(e) => return err!("Error: could not read license {}: {}", path, e),
+            },
+            None => None,
+        };
+        match schema.info.license.as_ref() {
+            Some(license) if Package::is_spdx(&license.name) => {
+                manifest.push_str(&format!("license = {:?}\n", license.name));
+            }
+            _ if text.is_some() => manifest.push_str("license-file = \"LICENSE\"\n"),
+            Some(license) => {
+                log::warn!(
+                    "License {} is not an SPDX expression and crate.license is not set, omitting it",
+                    license.name
+                );
+            }
+            None => {}
+        }
+        if let Some(text) = text {
+            std::fs::write(format!("{output}/LICENSE"), text)?;
+        }
+        let features = Tag::all()
+            .iter()
+            .filter_map(|tag| tag.feature())
+            .collect::<Vec<_>>();
+        manifest.push_str("\n[features]\n");
+        if features.is_empty() {
+            manifest.push_str("default = [\"native-tls\"]\n");
+        } else {
+            manifest.push_str("default = [\"full\", \"native-tls\"]\n");
+            manifest.push_str(&format!("full = {features:?}\n"));
+            for feature in features.iter() {
+                manifest.push_str(&format!("{feature} = []\n"));
+            }
+        }
+        manifest.push_str("native-tls = [\"dep:hyper-tls\"]\n");
+        manifest.push_str("rustls = [\"dep:hyper-rustls\"]\n");
+        if Config::get().mock {
+            manifest.push_str("mock = [\"hyper/server\"]\n");
+        }
+        if Config::get().service {
+            manifest.push_str("service = [\"hyper/server\"]\n");
+        }
+        manifest.push_str("\n[dependencies]\n");
+        for (dependency, version) in DEPENDENCIES {
+            manifest.push_str(&format!("{dependency} = {version}\n"));
+        }
+        std::fs::write(format!("{output}/Cargo.toml"), manifest)?;
+        std::fs::write(format!("{output}/README.md"), self.readme(schema))?;
+        Ok(())
+    }
+
+    fn is_spdx(expression: &str) -> bool {
+        let mut depth = 0;
+        for c in expression.chars() {
+            match c {
+                '(' => depth += 1,
+                ')' if depth > 0 => depth -= 1,
+                ')' => return false,
+                _ => {}
+            }
+        }
+        let expression = expression.replace(['(', ')'], " ");
+        let terms = expression.split_whitespace().collect::<Vec<_>>();
+        depth == 0
+            && terms.len() % 2 == 1
+            && terms.iter().enumerate().all(|(index, term)| {
+                if index % 2 == 1 {
+                    ["OR", "AND", "WITH"].contains(term)
+                } else {
+                    !["OR", "AND", "WITH"].contains(term)
+                        && term
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || ['-', '.', '+', ':'].contains(&c))
+                }
+            })
+    }
+
+    fn readme(&self, schema: &OpenAPI) -> String {
+        let mut readme = format!("# {}\n\n", schema.info.title);
+        if let Some(description) = schema.info.description.as_ref() {
+            readme.push_str(&format!("{}\n\n", description.trim()));
+        }
+        readme.push_str(&format!(
+            "Generated by colmenar from version {} of the OpenAPI document.\n",
+            schema.info.version
+        ));
+        if !schema.servers.is_empty() {
+            readme.push_str("\n## Servers\n\n");
+            for server in schema.servers.iter() {
+                match server.description.as_ref() {
+                    Some(description) => {
+                        readme.push_str(&format!("- {} ({description})\n", server.url))
+                    }
+                    None => readme.push_str(&format!("- {}\n", server.url)),
+                }
+            }
+        }
+        let tags = Tag::all();
+        if !tags.is_empty() {
+            readme.push_str("\n## Modules\n\n");
+            for tag in tags {
+                match tag.description.as_ref() {
+                    Some(description) => {
+                        readme.push_str(&format!("- `{}`: {}\n", tag.module(), description.trim()))
+                    }
+                    None => readme.push_str(&format!("- `{}`\n", tag.module())),
+                }
+            }
+        }
+        readme
+    }
+}
@@ -0,0 +1,81 @@
+use crate::prelude::*;
+use serde::Deserialize;
+
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Pagination {
+    pub page: String,
+    pub size: Option<String>,
+    pub per_page: Option<u64>,
+    #[serde(default = "Pagination::first")]
+    pub start: u64,
+    pub items: String,
+    pub max_pages: Option<u64>,
+}
+
+impl Pagination {
+    fn first() -> u64 {
+        1
+    }
+
+    pub fn discover(
+        name: &str,
+        keys: &[String],
+        schema: &openapiv3::Operation,
+    ) -> Result<Option<Pagination>, Error> {
+        let pagination = &Config::get().pagination;
+        if let Some(pagination) = keys.iter().find_map(|key| pagination.get(key)) {
+            return Ok(Some(pagination.clone()));
+        }
+        match schema.extensions.get("x-pagination") {
+            Some(value) => match serde_json::from_value(value.clone()) {
+                Ok(pagination) => Ok(Some(pagination)),
+                Err(e) => err!("Invalid x-pagination for '{name}': {e}"),
+            },
+            None => Ok(None),
+        }
+    }
+
+    pub fn item(&self, operation: &Operation, ty: &str) -> Result<(Property, Tokens), Error> {
+        let model = match Model::get(ty) {
+            Some(model) => model,
+            None => return err!("Paginated '{}' must return a model", operation.name),
+        };
+        let property = match model
+            .properties
+            .iter()
+            .find(|property| property.name == self.items.to_snake_case())
+        {
+            Some(property) => property.clone(),
+            None => {
+                return err!(
+                    "Paginated '{}' returns {ty} without an '{}' field",
+                    operation.name,
+                    self.items
+                )
+            }
+        };
+        let vec = property.ty.to_string()?;
+        let item = match vec
+            .strip_prefix("Vec<")
+            .and_then(|item| item.strip_suffix('>'))
+        {
+            Some(item) => item.to_string(),
+            None => {
+                return err!(
+                    "Field '{}' of {ty} must be an array to paginate '{}'",
+                    self.items,
+                    operation.name
+                )
+            }
+        };
+        let item = match Model::get(&item) {
+            Some(_) => {
+                let module = import("super", item);
+                quote!($module)
+            }
+            None => quote!($item),
+        };
+        Ok((property, item))
+    }
+}
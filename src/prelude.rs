@@ -1,12 +1,17 @@
-pub use crate::constants::{GREEK, KEYWORDS};
-pub use crate::{err, Array, Enumeration, Error, Model, Operation, Parameter, Property};
+pub use crate::constants::{
+    GREEK, KEYWORDS, RESERVED, RESERVED_FEATURES, RESERVED_FIELDS, RESERVED_MODULES,
+};
+pub use crate::{
+    err, Array, Body, Config, Content, Enumeration, Error, Header, Layout, Mock, Model, Operation,
+    Package, Pagination, Parameter, Property, Response, Security, Server, Service, Status, Tag,
+};
 pub use genco::{
     prelude::rust::{self, import, Tokens},
     quote, quote_in,
     tokens::quoted,
 };
 pub use heck::*;
-use std::process::Command;
+use std::{collections::BTreeSet, process::Command};
 
 pub fn write_tokens(path: &str, tokens: Tokens) -> Result<(), Error> {
     let path = std::path::Path::new(path);
@@ -16,12 +21,64 @@ pub fn write_tokens(path: &str, tokens: Tokens) -> Result<(), Error> {
     Ok(())
 }
 
+pub fn write_qualified_tokens(path: &str, tokens: Tokens) -> Result<(), Error> {
+    let path = std::path::Path::new(path);
+    let dir = path.parent().unwrap();
+    std::fs::create_dir_all(dir)?;
+    let mut w = genco::fmt::FmtWriter::new(String::new());
+    let fmt = genco::fmt::Config::from_lang::<rust::Rust>();
+    let config = rust::Config::default().with_default_import(rust::ImportMode::Qualified);
+    tokens.format_file(&mut w.as_formatter(&fmt), &config)?;
+    std::fs::write(path, w.into_inner())?;
+    Ok(())
+}
+
+pub fn identifiers(tokens: &Tokens) -> Result<BTreeSet<String>, Error> {
+    let source = tokens.to_string()?;
+    let mut identifiers = BTreeSet::new();
+    let mut identifier = String::new();
+    let mut chars = source.chars();
+    while let Some(c) = chars.next() {
+        if c.is_alphanumeric() || c == '_' {
+            identifier.push(c);
+            continue;
+        }
+        if !identifier.is_empty() {
+            identifiers.insert(std::mem::take(&mut identifier));
+        }
+        if c == '"' {
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => {
+                        chars.next();
+                    }
+                    '"' => break,
+                    _ => {}
+                }
+            }
+        }
+    }
+    if !identifier.is_empty() {
+        identifiers.insert(identifier);
+    }
+    Ok(identifiers)
+}
+
+pub fn cfg(features: &BTreeSet<String>) -> Tokens {
+    if features.is_empty() {
+        return Tokens::new();
+    }
+    quote!(
+        #[cfg(any($(for feature in features join (, ) => feature = $(quoted(feature)))))]
+    )
+}
+
 pub fn format(dir: &str) -> Result<(), Error> {
-    println!("Formatting...");
+    log::info!("Formatting {dir}");
     let output = Command::new("bash")
         .args([
             "-c",
-            &format!("find {dir} -type f | xargs rustfmt --edition 2021",),
+            &format!("find {dir} -type f -name '*.rs' | xargs rustfmt --edition 2021",),
         ])
         .output()?;
     if !output.status.success() {
@@ -30,6 +87,9 @@ pub fn format(dir: &str) -> Result<(), Error> {
             String::from_utf8_lossy(&output.stderr)
         );
     }
-    println!("{}", String::from_utf8_lossy(&output.stdout));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if !stdout.trim().is_empty() {
+        log::debug!("{}", stdout.trim());
+    }
     Ok(())
 }
@@ -45,7 +45,7 @@ impl Property {
                     ..
                 } => {
                     property.description = description.clone();
-                    let ty = reference.split("/").last().unwrap().to_string();
+                    let ty = Model::reference(reference);
                     property.ty = quote!($ty);
                 }
                 ReferenceOr::Item(item) => {
@@ -0,0 +1,293 @@
+use crate::prelude::*;
+use once_cell::sync::OnceCell;
+use openapiv3::{ParameterSchemaOrContent, ReferenceOr, SchemaKind, StatusCode, Type};
+use std::{collections::BTreeMap, sync::Mutex};
+
+static RESPONSES: OnceCell<Mutex<BTreeMap<String, Response>>> = OnceCell::new();
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Status {
+    Code(u16),
+    Range(u16),
+    Default,
+}
+
+impl Status {
+    pub fn is_success(&self) -> bool {
+        match self {
+            Status::Code(code) => (200..300).contains(code),
+            Status::Range(range) => *range == 2,
+            Status::Default => false,
+        }
+    }
+
+    pub fn variant(&self) -> String {
+        match self {
+            Status::Code(code) => match hyper::StatusCode::from_u16(*code)
+                .ok()
+                .and_then(|status| status.canonical_reason())
+            {
+                Some(reason) => reason.to_upper_camel_case(),
+                None => format!("Status{code}"),
+            },
+            Status::Range(range) => format!("Status{range}XX"),
+            Status::Default => "Default".to_string(),
+        }
+    }
+
+    pub fn pattern(&self) -> Tokens {
+        match self {
+            Status::Code(code) => quote!($(code.to_string())),
+            Status::Range(range) => {
+                quote!($((range * 100).to_string())..=$((range * 100 + 99).to_string()))
+            }
+            Status::Default => quote!(_),
+        }
+    }
+}
+
+impl From<&StatusCode> for Status {
+    fn from(status: &StatusCode) -> Self {
+        match status {
+            StatusCode::Code(code) => Status::Code(*code),
+            StatusCode::Range(range) => Status::Range(*range),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub enum Body {
+    Empty,
+    Json(String),
+    Text,
+    Binary,
+    Events(Option<String>),
+    Lines(Option<String>),
+    Records(Option<String>),
+}
+
+impl Body {
+    pub fn is_streaming(&self) -> bool {
+        matches!(
+            self,
+            Body::Binary | Body::Events(_) | Body::Lines(_) | Body::Records(_)
+        )
+    }
+
+    pub fn ty(&self) -> Option<Tokens> {
+        let item = |ty: &Option<String>| match ty {
+            Some(ty) => {
+                let module = import("super", ty);
+                quote!($module)
+            }
+            None => quote!(Value),
+        };
+        match self {
+            Body::Empty => None,
+            Body::Json(ty) => {
+                let module = import("super", ty);
+                Some(quote!($module))
+            }
+            Body::Text => Some(quote!(String)),
+            Body::Binary => Some(quote!(hyper::Body)),
+            Body::Events(ty) | Body::Lines(ty) | Body::Records(ty) => {
+                Some(quote!(EventStream<$(item(ty))>))
+            }
+        }
+    }
+
+    pub fn decode(&self) -> Option<Tokens> {
+        match self {
+            Body::Empty => None,
+            Body::Json(_) => Some(quote!(decode(&bytes(response).await?)?)),
+            Body::Text => Some(quote!(text(response).await?)),
+            Body::Binary => Some(quote!(response.into_body())),
+            Body::Events(_) => Some(quote!(EventStream::events(response.into_body()))),
+            Body::Lines(_) => Some(quote!(EventStream::lines(response.into_body()))),
+            Body::Records(_) => Some(quote!(EventStream::records(response.into_body()))),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Header {
+    pub name: String,
+    pub field: String,
+    pub ty: Tokens,
+    pub description: Option<String>,
+    pub required: bool,
+}
+
+impl Header {
+    fn discover(name: &str, schema: &ReferenceOr<openapiv3::Header>) -> Self {
+        let field = name.to_snake_case();
+        let field = if KEYWORDS.contains(&field.as_str()) {
+            format!("r#{field}")
+        } else {
+            field
+        };
+        let (ty, description, required) = match schema {
+            ReferenceOr::Reference { .. } => (quote!(String), None, false),
+            ReferenceOr::Item(header) => {
+                let ty = match &header.format {
+                    ParameterSchemaOrContent::Schema(ReferenceOr::Item(schema)) => {
+                        match &schema.schema_kind {
+                            SchemaKind::Type(Type::Boolean {}) => quote!(bool),
+                            SchemaKind::Type(Type::Integer(_)) => quote!(i64),
+                            SchemaKind::Type(Type::Number(_)) => quote!(f64),
+                            _ => quote!(String),
+                        }
+                    }
+                    _ => quote!(String),
+                };
+                (ty, header.description.clone(), header.required)
+            }
+        };
+        Self {
+            name: name.to_lowercase(),
+            field,
+            ty,
+            description,
+            required,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Response {
+    pub status: Status,
+    pub description: String,
+    pub body: Body,
+    pub headers: Vec<Header>,
+}
+
+impl Response {
+    fn add(name: &str, response: Response) {
+        let mut responses = RESPONSES
+            .get_or_init(|| Mutex::new(BTreeMap::new()))
+            .lock()
+            .unwrap();
+        if responses.contains_key(name) {
+            panic!("Response {} already exists", name);
+        }
+        responses.insert(name.to_string(), response);
+    }
+
+    pub fn get(name: &str) -> Option<Response> {
+        RESPONSES
+            .get_or_init(|| Mutex::new(BTreeMap::new()))
+            .lock()
+            .unwrap()
+            .get(name)
+            .cloned()
+    }
+
+    pub fn success(responses: &[Response]) -> Vec<Response> {
+        let mut success = responses
+            .iter()
+            .filter(|response| response.status.is_success())
+            .cloned()
+            .collect::<Vec<_>>();
+        if success.is_empty() {
+            success = responses
+                .iter()
+                .filter(|response| response.status == Status::Default)
+                .cloned()
+                .collect();
+        }
+        success.sort_by(|a, b| a.status.cmp(&b.status));
+        success
+    }
+
+    pub fn errors(responses: &[Response]) -> Vec<Response> {
+        let success = Response::success(responses);
+        let mut errors = responses
+            .iter()
+            .filter(|response| !success.iter().any(|s| s.status == response.status))
+            .cloned()
+            .collect::<Vec<_>>();
+        errors.sort_by(|a, b| a.status.cmp(&b.status));
+        errors
+    }
+
+    pub fn discover_component(name: &str, schema: &openapiv3::Response) -> Result<(), Error> {
+        let ty = format!("response_{name}").to_upper_camel_case();
+        let response = Self::discover_item(&ty, Status::Default, schema)?;
+        Response::add(name, response);
+        Ok(())
+    }
+
+    pub fn discover(
+        name: &str,
+        status: Status,
+        schema: &ReferenceOr<openapiv3::Response>,
+    ) -> Result<Self, Error> {
+        match schema {
+            ReferenceOr::Reference { reference, .. } => {
+                let reference = reference.split('/').last().unwrap();
+                match Response::get(reference) {
+                    Some(response) => Ok(Self { status, ..response }),
+                    None => err!("Unknown response reference: {reference}"),
+                }
+            }
+            ReferenceOr::Item(item) => Self::discover_item(name, status, item),
+        }
+    }
+
+    fn discover_item(
+        name: &str,
+        status: Status,
+        schema: &openapiv3::Response,
+    ) -> Result<Self, Error> {
+        let media = schema
+            .content
+            .get("application/json")
+            .map(|media| ("application/json".to_string(), media))
+            .or_else(|| {
+                schema.content.iter().next().map(|(media_type, media)| {
+                    let media_type = media_type.split(';').next().unwrap().trim();
+                    (media_type.to_lowercase(), media)
+                })
+            });
+        let body = match media {
+            None => Body::Empty,
+            Some((media_type, media)) => {
+                let ty = match media.schema.as_ref() {
+                    None => None,
+                    Some(ReferenceOr::Reference { reference, .. }) => {
+                        Some(Model::reference(reference))
+                    }
+                    Some(ReferenceOr::Item(item)) => {
+                        Model::discover(name, item)?;
+                        Some(name.to_string())
+                    }
+                };
+                match media_type.as_str() {
+                    "application/json" => match ty {
+                        Some(ty) => Body::Json(ty),
+                        None => Body::Empty,
+                    },
+                    "text/event-stream" => Body::Events(ty),
+                    "application/x-ndjson" | "application/jsonl" => Body::Lines(ty),
+                    "application/json-seq" => Body::Records(ty),
+                    _ if media_type.ends_with("+json") => match ty {
+                        Some(ty) => Body::Json(ty),
+                        None => Body::Empty,
+                    },
+                    _ if media_type.starts_with("text/") => Body::Text,
+                    _ => Body::Binary,
+                }
+            }
+        };
+        Ok(Self {
+            status,
+            description: schema.description.clone(),
+            body,
+            headers: schema
+                .headers
+                .iter()
+                .map(|(name, header)| Header::discover(name, header))
+                .collect(),
+        })
+    }
+}
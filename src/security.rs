@@ -0,0 +1,490 @@
+use crate::prelude::*;
+use once_cell::sync::OnceCell;
+use openapiv3::{APIKeyLocation, SecurityRequirement, SecurityScheme};
+use std::{collections::BTreeMap, sync::Mutex};
+
+static SECURITY: OnceCell<Mutex<BTreeMap<String, Security>>> = OnceCell::new();
+static REQUIREMENTS: OnceCell<Vec<Vec<String>>> = OnceCell::new();
+
+#[derive(Clone)]
+pub enum Scheme {
+    Bearer,
+    Basic,
+    Header(String),
+    Query(String),
+    Cookie(String),
+    ClientCredentials(String),
+}
+
+#[derive(Clone)]
+pub struct Security {
+    pub name: String,
+    pub description: Option<String>,
+    pub scheme: Scheme,
+}
+
+impl Security {
+    pub fn all() -> Vec<Security> {
+        SECURITY
+            .get_or_init(|| Mutex::new(BTreeMap::new()))
+            .lock()
+            .unwrap()
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    fn add(security: Security) {
+        let mut securities = SECURITY
+            .get_or_init(|| Mutex::new(BTreeMap::new()))
+            .lock()
+            .unwrap();
+        if securities.contains_key(&security.name) {
+            panic!("Security scheme {} already exists", security.name);
+        }
+        securities.insert(security.name.clone(), security);
+    }
+
+    pub fn discover(name: &str, schema: &SecurityScheme) -> Result<(), Error> {
+        let unsupported = |kind: &str| {
+            log::warn!(
+                "Security scheme '{name}' uses unsupported {kind}, operations requiring it will fail with missing credentials"
+            );
+            Ok(())
+        };
+        let (scheme, description) = match schema {
+            SecurityScheme::HTTP {
+                scheme,
+                description,
+                ..
+            } => match scheme.to_lowercase().as_str() {
+                "bearer" => (Scheme::Bearer, description),
+                "basic" => (Scheme::Basic, description),
+                _ => return unsupported(&format!("HTTP scheme '{scheme}'")),
+            },
+            SecurityScheme::APIKey {
+                location,
+                name: key,
+                description,
+                ..
+            } => match location {
+                APIKeyLocation::Header => (Scheme::Header(key.clone()), description),
+                APIKeyLocation::Query => (Scheme::Query(key.clone()), description),
+                APIKeyLocation::Cookie => (Scheme::Cookie(key.clone()), description),
+            },
+            SecurityScheme::OAuth2 {
+                flows, description, ..
+            } => match flows.client_credentials.as_ref() {
+                Some(flow) => (
+                    Scheme::ClientCredentials(flow.token_url.clone()),
+                    description,
+                ),
+                None => return unsupported("OAuth2 flows, only clientCredentials is generated"),
+            },
+            SecurityScheme::OpenIDConnect { .. } => return unsupported("OpenID Connect"),
+        };
+        Security::add(Security {
+            name: name.to_string(),
+            description: description.clone(),
+            scheme,
+        });
+        Ok(())
+    }
+
+    pub fn requirements(requirements: &[SecurityRequirement]) -> Vec<Vec<String>> {
+        requirements
+            .iter()
+            .map(|requirement| requirement.keys().cloned().collect())
+            .collect()
+    }
+
+    pub fn set_default(requirements: &[SecurityRequirement]) -> Result<(), Error> {
+        if REQUIREMENTS
+            .set(Security::requirements(requirements))
+            .is_err()
+        {
+            return err!("Error: default security has already been set");
+        }
+        Ok(())
+    }
+
+    pub fn default_requirements() -> Vec<Vec<String>> {
+        REQUIREMENTS.get().cloned().unwrap_or_default()
+    }
+
+    pub fn requirement_tokens(requirements: &[Vec<String>]) -> Tokens {
+        quote!(&[$(for requirement in requirements join (, ) => &[$(for name in requirement join (, ) => $(quoted(name)))])])
+    }
+
+    pub fn redacted() -> Vec<String> {
+        Security::all()
+            .into_iter()
+            .filter_map(|security| match security.scheme {
+                Scheme::Header(name) => Some(name.to_lowercase()),
+                Scheme::Query(name) => Some(name),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn field(&self) -> String {
+        let field = self.name.to_snake_case();
+        if KEYWORDS.contains(&field.as_str()) {
+            format!("r#{field}")
+        } else if RESERVED_FIELDS.contains(&field.as_str()) {
+            format!("{field}_scheme")
+        } else {
+            field
+        }
+    }
+
+    fn env(&self) -> Tokens {
+        let field = self.field();
+        let var = self.name.to_shouty_snake_case();
+        match self.scheme {
+            Scheme::Basic => quote!(
+                if let Ok(username) = std::env::var(format!($(quoted(format!("{{prefix}}_{var}_USERNAME"))))) {
+                    credentials.$(&field) = Some(BasicAuth {
+                        username,
+                        password: std::env::var(format!($(quoted(format!("{{prefix}}_{var}_PASSWORD"))))).ok(),
+                    });
+                }
+            ),
+            Scheme::ClientCredentials(_) => quote!(
+                if let (Ok(client_id), Ok(client_secret)) = (
+                    std::env::var(format!($(quoted(format!("{{prefix}}_{var}_CLIENT_ID"))))),
+                    std::env::var(format!($(quoted(format!("{{prefix}}_{var}_CLIENT_SECRET"))))),
+                ) {
+                    credentials.$(&field) = Some(ClientCredentials::new(client_id, client_secret));
+                }
+            ),
+            _ => quote!(
+                if let Ok(value) = std::env::var(format!($(quoted(format!("{{prefix}}_{var}"))))) {
+                    credentials.$(&field) = Some(value);
+                }
+            ),
+        }
+    }
+
+    fn ty(&self) -> Tokens {
+        match self.scheme {
+            Scheme::Basic => quote!(BasicAuth),
+            Scheme::ClientCredentials(_) => quote!(ClientCredentials),
+            _ => quote!(String),
+        }
+    }
+
+    fn setter(&self) -> Tokens {
+        let field = self.field();
+        let doc = self
+            .description
+            .as_ref()
+            .map(|description| quote!(#[doc = $(quoted(description))]));
+        match self.scheme {
+            Scheme::Basic => quote!(
+                $doc
+                pub fn $(&field)<S: AsRef<str>>(mut self, username: S, password: Option<S>) -> Self {
+                    self.$(&field) = Some(BasicAuth {
+                        username: username.as_ref().to_string(),
+                        password: password.map(|password| password.as_ref().to_string()),
+                    });
+                    self
+                }
+            ),
+            Scheme::ClientCredentials(_) => quote!(
+                $doc
+                pub fn $(&field)(mut self, credentials: ClientCredentials) -> Self {
+                    self.$(&field) = Some(credentials);
+                    self
+                }
+            ),
+            _ => quote!(
+                $doc
+                pub fn $(&field)<S: AsRef<str>>(mut self, value: S) -> Self {
+                    self.$(&field) = Some(value.as_ref().to_string());
+                    self
+                }
+            ),
+        }
+    }
+
+    fn apply(&self) -> Tokens {
+        let field = self.field();
+        let value = quote!(if let Some(value) = &credentials.$(&field));
+        match &self.scheme {
+            Scheme::Bearer => quote!(
+                $value {
+                    request = request.header("authorization", format!("Bearer {value}"));
+                }
+            ),
+            Scheme::Basic => quote!(
+                $value {
+                    request = request.header("authorization", value.header());
+                }
+            ),
+            Scheme::Header(name) => quote!(
+                $value {
+                    request = request.header($(quoted(name)), value);
+                }
+            ),
+            Scheme::Query(name) => quote!(
+                $value {
+                    uri.push(if uri.contains('?') { '&' } else { '?' });
+                    uri.push_str(&serde_urlencoded::to_string([($(quoted(name)), value)])?);
+                }
+            ),
+            Scheme::Cookie(name) => quote!(
+                $value {
+                    cookies.push(format!("{}={value}", $(quoted(name))));
+                }
+            ),
+            Scheme::ClientCredentials(url) => quote!(
+                $value {
+                    let token = self.access_token($(quoted(&self.name)), value, $(quoted(url))).await?;
+                    request = request.header("authorization", format!("Bearer {token}"));
+                }
+            ),
+        }
+    }
+
+    pub fn tokens() -> Tokens {
+        let securities = Security::all();
+        quote!(
+            #[derive(Clone, Default)]
+            pub struct Credentials {
+                $(for security in &securities =>
+                    $(security.field()): Option<$(security.ty())>,
+                )
+            }
+            $['\n']
+            impl Credentials {
+                $(for security in &securities =>
+                    $(security.setter())
+                    $['\n']
+                )
+                pub fn from_env<S: AsRef<str>>(prefix: S) -> Self {
+                    let prefix = prefix.as_ref();
+                    let mut credentials = Self::default();
+                    $(for security in &securities =>
+                        $(security.env())
+                    )
+                    credentials
+                }
+                $['\n']
+                fn has(&self, scheme: &str) -> bool {
+                    match scheme {
+                        $(for security in &securities =>
+                            $(quoted(&security.name)) => self.$(security.field()).is_some(),
+                        )
+                        _ => false,
+                    }
+                }
+            }
+            $['\n']
+            pub trait CredentialProvider: Send + Sync {
+                fn credentials(&self) -> BoxFuture<'_, Result<Credentials, Error>>;
+                $['\n']
+                fn refresh(&self) -> BoxFuture<'_, Result<Credentials, Error>> {
+                    self.credentials()
+                }
+            }
+            $['\n']
+            impl CredentialProvider for Credentials {
+                fn credentials(&self) -> BoxFuture<'_, Result<Credentials, Error>> {
+                    future::ready(Ok(self.clone())).boxed()
+                }
+            }
+            $['\n']
+            pub struct EnvCredentials {
+                prefix: String,
+            }
+            $['\n']
+            impl EnvCredentials {
+                pub fn new<S: AsRef<str>>(prefix: S) -> Self {
+                    Self {
+                        prefix: prefix.as_ref().to_string(),
+                    }
+                }
+            }
+            $['\n']
+            impl CredentialProvider for EnvCredentials {
+                fn credentials(&self) -> BoxFuture<'_, Result<Credentials, Error>> {
+                    future::ready(Ok(Credentials::from_env(&self.prefix))).boxed()
+                }
+            }
+            $['\n']
+            pub struct CallbackCredentials<F> {
+                callback: F,
+            }
+            $['\n']
+            impl<F, R> CallbackCredentials<F>
+            where
+                F: Fn() -> R + Send + Sync,
+                R: Future<Output = Result<Credentials, Error>> + Send + 'static,
+            {
+                pub fn new(callback: F) -> Self {
+                    Self { callback }
+                }
+            }
+            $['\n']
+            impl<F, R> CredentialProvider for CallbackCredentials<F>
+            where
+                F: Fn() -> R + Send + Sync,
+                R: Future<Output = Result<Credentials, Error>> + Send + 'static,
+            {
+                fn credentials(&self) -> BoxFuture<'_, Result<Credentials, Error>> {
+                    (self.callback)().boxed()
+                }
+            }
+            $['\n']
+            #[derive(Clone)]
+            pub struct BasicAuth {
+                pub username: String,
+                pub password: Option<String>,
+            }
+            $['\n']
+            impl BasicAuth {
+                fn header(&self) -> String {
+                    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+                    let input = format!("{}:{}", self.username, self.password.as_deref().unwrap_or(""));
+                    let mut encoded = String::new();
+                    for chunk in input.as_bytes().chunks(3) {
+                        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+                        let bits = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+                        for i in 0..4 {
+                            if i <= chunk.len() {
+                                encoded.push(ALPHABET[(bits >> (18 - 6 * i) & 63) as usize] as char);
+                            } else {
+                                encoded.push('=');
+                            }
+                        }
+                    }
+                    format!("Basic {encoded}")
+                }
+            }
+            $['\n']
+            #[derive(Clone)]
+            pub struct ClientCredentials {
+                pub client_id: String,
+                pub client_secret: String,
+                pub token_url: Option<String>,
+                pub scopes: Vec<String>,
+            }
+            $['\n']
+            impl ClientCredentials {
+                pub fn new<S: AsRef<str>>(client_id: S, client_secret: S) -> Self {
+                    Self {
+                        client_id: client_id.as_ref().to_string(),
+                        client_secret: client_secret.as_ref().to_string(),
+                        token_url: None,
+                        scopes: Vec::new(),
+                    }
+                }
+                $['\n']
+                pub fn token_url<S: AsRef<str>>(mut self, token_url: S) -> Self {
+                    self.token_url = Some(token_url.as_ref().to_string());
+                    self
+                }
+                $['\n']
+                pub fn scopes<S: AsRef<str>>(mut self, scopes: &[S]) -> Self {
+                    self.scopes = scopes.iter().map(|scope| scope.as_ref().to_string()).collect();
+                    self
+                }
+            }
+            $['\n']
+            #[derive(Deserialize)]
+            struct AccessToken {
+                access_token: String,
+                expires_in: Option<u64>,
+            }
+        )
+    }
+
+    pub fn authorize() -> Tokens {
+        quote!(
+            async fn authorize(
+                &self,
+                credentials: &Credentials,
+                security: &[&[&str]],
+                uri: &mut String,
+                mut request: hyper::http::request::Builder,
+            ) -> Result<hyper::http::request::Builder, Error> {
+                if security.is_empty() {
+                    return Ok(request);
+                }
+                let requirement = match security
+                    .iter()
+                    .filter(|requirement| !requirement.is_empty())
+                    .find(|requirement| requirement.iter().all(|scheme| credentials.has(scheme)))
+                {
+                    Some(requirement) => *requirement,
+                    None if security.iter().any(|requirement| requirement.is_empty()) => {
+                        return Ok(request)
+                    }
+                    None => {
+                        return Err(Error::Validation(format!(
+                            "missing credentials for one of {security:?}"
+                        )))
+                    }
+                };
+                let mut cookies: Vec<String> = Vec::new();
+                for scheme in requirement {
+                    match *scheme {
+                        $(for security in Security::all() =>
+                            $(quoted(&security.name)) => {
+                                $(security.apply())
+                            }
+                        )
+                        _ => {}
+                    }
+                }
+                if !cookies.is_empty() {
+                    request = request.header("cookie", cookies.join("; "));
+                }
+                Ok(request)
+            }
+            $['\n']
+            async fn access_token(
+                &self,
+                scheme: &str,
+                credentials: &ClientCredentials,
+                token_url: &str,
+            ) -> Result<String, Error> {
+                if let Some((token, expires)) = self.tokens.lock().unwrap().get(scheme) {
+                    if *expires > Instant::now() {
+                        return Ok(token.clone());
+                    }
+                }
+                let scopes = credentials.scopes.join(" ");
+                let mut form = vec![
+                    ("grant_type", "client_credentials"),
+                    ("client_id", credentials.client_id.as_str()),
+                    ("client_secret", credentials.client_secret.as_str()),
+                ];
+                if !scopes.is_empty() {
+                    form.push(("scope", scopes.as_str()));
+                }
+                let request = hyper::Request::builder()
+                    .method(Method::POST)
+                    .uri(credentials.token_url.as_deref().unwrap_or(token_url))
+                    .header("content-type", "application/x-www-form-urlencoded")
+                    .body(hyper::Body::from(serde_urlencoded::to_string(&form)?))
+                    .map_err(|e| Error::Validation(e.to_string()))?;
+                let response = self.transport.send(request).await?;
+                if !response.status().is_success() {
+                    return Err(Error::Status {
+                        code: response.status(),
+                        body: bytes(response).await?,
+                    });
+                }
+                let token: AccessToken = decode(&bytes(response).await?)?;
+                let lifetime = token.expires_in.unwrap_or(3600).saturating_sub(30);
+                self.tokens.lock().unwrap().insert(
+                    scheme.to_string(),
+                    (token.access_token.clone(), Instant::now() + Duration::from_secs(lifetime)),
+                );
+                Ok(token.access_token)
+            }
+        )
+    }
+}
@@ -0,0 +1,285 @@
+use crate::prelude::*;
+use once_cell::sync::OnceCell;
+use std::{collections::BTreeMap, sync::Mutex};
+
+static SERVERS: OnceCell<Mutex<BTreeMap<String, Server>>> = OnceCell::new();
+
+#[derive(Clone)]
+pub struct Variable {
+    pub name: String,
+    pub description: Option<String>,
+    pub default: String,
+    pub values: Vec<String>,
+}
+
+#[derive(Clone)]
+pub struct Server {
+    pub name: String,
+    pub index: usize,
+    pub url: String,
+    pub description: Option<String>,
+    pub variables: Vec<Variable>,
+}
+
+impl Server {
+    pub fn all() -> Vec<Server> {
+        let mut servers = SERVERS
+            .get_or_init(|| Mutex::new(BTreeMap::new()))
+            .lock()
+            .unwrap()
+            .values()
+            .cloned()
+            .collect::<Vec<_>>();
+        servers.sort_by_key(|server| server.index);
+        servers
+    }
+
+    fn add(server: Server) {
+        let mut servers = SERVERS
+            .get_or_init(|| Mutex::new(BTreeMap::new()))
+            .lock()
+            .unwrap();
+        if servers.contains_key(&server.name) {
+            panic!("Server {} already exists", server.name);
+        }
+        servers.insert(server.name.clone(), server);
+    }
+
+    pub fn get(name: &str) -> Option<Server> {
+        SERVERS
+            .get_or_init(|| Mutex::new(BTreeMap::new()))
+            .lock()
+            .unwrap()
+            .get(name)
+            .cloned()
+    }
+
+    fn synthesize_name(schema: &openapiv3::Server, index: usize) -> String {
+        if let Some(description) = schema.description.as_ref() {
+            if description.split_whitespace().count() <= 4 {
+                let name = description.to_upper_camel_case();
+                if name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+                    return name;
+                }
+            }
+        }
+        let host = schema
+            .url
+            .split_once("://")
+            .map(|(_, rest)| rest)
+            .unwrap_or(&schema.url);
+        let host = host.split('/').next().unwrap_or_default();
+        let name = host
+            .replace(['{', '}'], "")
+            .split(['.', ':'])
+            .filter(|part| !["www", "com", "io", "org", "net"].contains(part))
+            .collect::<Vec<_>>()
+            .join("_")
+            .to_upper_camel_case();
+        if name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            name
+        } else {
+            format!("Server{index}")
+        }
+    }
+
+    pub fn discover(schema: &openapiv3::Server) -> Result<String, Error> {
+        let servers = Server::all();
+        if let Some(server) = servers.iter().find(|server| server.url == schema.url) {
+            return Ok(server.name.clone());
+        }
+        let index = servers.len();
+        let mut name = Server::synthesize_name(schema, index);
+        if name == "Custom" || Server::get(&name).is_some() {
+            name = format!("{name}{index}");
+        }
+        let mut variables = Vec::new();
+        for (variable, item) in schema.variables.iter().flatten() {
+            if !schema.url.contains(&format!("{{{variable}}}")) {
+                return err!("Server variable '{variable}' is not used in {}", schema.url);
+            }
+            if !item.enumeration.is_empty() && !item.enumeration.contains(&item.default) {
+                return err!(
+                    "Default '{}' of server variable '{variable}' is not one of its values",
+                    item.default
+                );
+            }
+            variables.push(Variable {
+                name: variable.clone(),
+                description: item.description.clone(),
+                default: item.default.clone(),
+                values: item.enumeration.clone(),
+            });
+        }
+        Server::add(Server {
+            name: name.clone(),
+            index,
+            url: schema.url.clone(),
+            description: schema.description.clone(),
+            variables,
+        });
+        Ok(name)
+    }
+
+    pub fn value(name: &str) -> Tokens {
+        match Server::get(name) {
+            Some(server) if !server.variables.is_empty() => {
+                quote!(Server::$(server.name.to_snake_case())())
+            }
+            _ => quote!(Server::$name),
+        }
+    }
+
+    fn field(variable: &Variable) -> String {
+        let field = variable.name.to_snake_case();
+        if KEYWORDS.contains(&field.as_str()) {
+            format!("r#{field}")
+        } else {
+            field
+        }
+    }
+
+    fn variable_type(&self, variable: &Variable) -> Tokens {
+        if variable.values.is_empty() {
+            quote!(String)
+        } else {
+            quote!($(format!("{}_{}", self.name, variable.name).to_upper_camel_case()))
+        }
+    }
+
+    fn variants(&self, variable: &Variable) -> Vec<String> {
+        variable
+            .values
+            .iter()
+            .map(|value| {
+                if value.starts_with(|c: char| c.is_ascii_digit()) {
+                    format!("{}_{}_{value}", self.name, variable.name).to_upper_camel_case()
+                } else {
+                    value.to_upper_camel_case()
+                }
+            })
+            .collect()
+    }
+
+    fn variable_tokens(&self, variable: &Variable) -> Tokens {
+        if variable.values.is_empty() {
+            return Tokens::new();
+        }
+        let ty = self.variable_type(variable);
+        let variants = self.variants(variable);
+        let default = &variants[variable
+            .values
+            .iter()
+            .position(|value| *value == variable.default)
+            .unwrap()];
+        quote!(
+            $(variable.description.as_ref().map(|description| quote!(#[doc = $(quoted(description))])))
+            #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+            pub enum $(&ty) {
+                $(for variant in &variants => $variant,)
+            }
+            $['\n']
+            impl $(&ty) {
+                pub fn as_str(&self) -> &'static str {
+                    match self {
+                        $(for (variant, value) in variants.iter().zip(&variable.values) =>
+                            Self::$variant => $(quoted(value)),
+                        )
+                    }
+                }
+            }
+            $['\n']
+            impl Default for $(&ty) {
+                fn default() -> Self {
+                    Self::$default
+                }
+            }
+            $['\n']
+        )
+    }
+
+    fn url_tokens(&self) -> Tokens {
+        let mut url = quote!($(quoted(&self.url)).to_string());
+        for variable in self.variables.iter() {
+            let field = Server::field(variable);
+            let value = if variable.values.is_empty() {
+                quote!($field)
+            } else {
+                quote!($field.as_str())
+            };
+            url = quote!($url.replace($(quoted(format!("{{{}}}", variable.name))), $value));
+        }
+        url
+    }
+
+    pub fn tokens(default: Option<&openapiv3::Server>) -> Tokens {
+        let servers = Server::all();
+        let default = match default
+            .and_then(|default| servers.iter().find(|server| server.url == default.url))
+        {
+            Some(server) => Server::value(&server.name),
+            None => quote!(Server::Custom(String::new())),
+        };
+        quote!(
+            $(for server in servers.iter() =>
+                $(for variable in server.variables.iter() => $(server.variable_tokens(variable)))
+            )
+            #[derive(Debug, Clone, PartialEq, Eq)]
+            pub enum Server {
+                $(for server in servers.iter() =>
+                    $(server.description.as_ref().map(|description| quote!(#[doc = $(quoted(description))])))
+                    $(if server.variables.is_empty() {
+                        $(&server.name),
+                    } else {
+                        $(&server.name) {
+                            $(for variable in server.variables.iter() =>
+                                $(Server::field(variable)): $(server.variable_type(variable)),
+                            )
+                        },
+                    })
+                )
+                #[doc = "Any other base URL, such as a staging deployment or a local mock"]
+                Custom(String),
+            }
+            $['\n']
+            impl Server {
+                $(for server in servers.iter().filter(|server| !server.variables.is_empty()) =>
+                    #[doc = $(quoted(format!("{} with every variable at its default", server.url)))]
+                    pub fn $(server.name.to_snake_case())() -> Self {
+                        Server::$(&server.name) {
+                            $(for variable in server.variables.iter() =>
+                                $(Server::field(variable)):
+                                $(if variable.values.is_empty() {
+                                    $(quoted(&variable.default)).to_string(),
+                                } else {
+                                    Default::default(),
+                                })
+                            )
+                        }
+                    }
+                    $['\n']
+                )
+                pub fn url(&self) -> String {
+                    match self {
+                        $(for server in servers.iter() =>
+                            $(if server.variables.is_empty() {
+                                Server::$(&server.name) => $(server.url_tokens()),
+                            } else {
+                                Server::$(&server.name) {
+                                    $(for variable in server.variables.iter() => $(Server::field(variable)),)
+                                } => $(server.url_tokens()),
+                            })
+                        )
+                        Server::Custom(url) => url.clone(),
+                    }
+                }
+            }
+            $['\n']
+            impl Default for Server {
+                fn default() -> Self {
+                    $default
+                }
+            }
+        )
+    }
+}
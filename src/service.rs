@@ -0,0 +1,549 @@
+use crate::prelude::*;
+use std::collections::BTreeSet;
+
+pub struct Service;
+
+impl Service {
+    fn status(status: &Status) -> String {
+        match status {
+            Status::Code(code) => code.to_string(),
+            Status::Range(range) => (range * 100).to_string(),
+            Status::Default => "200".to_string(),
+        }
+    }
+
+    fn is_raw(operation: &Operation) -> bool {
+        operation.is_enumeration()
+            && Response::success(&operation.responses)
+                .iter()
+                .any(|response| response.body.is_streaming())
+    }
+
+    fn is_multipart(operation: &Operation) -> bool {
+        operation
+            .request
+            .iter()
+            .any(|content| matches!(content, Content::Multipart))
+    }
+
+    fn headers_type(operation: &Operation) -> Option<Tokens> {
+        if operation.is_metadata() || Service::is_raw(operation) {
+            return None;
+        }
+        operation.headers_type()
+    }
+
+    fn response_type(operation: &Operation) -> Tokens {
+        if operation.is_metadata() {
+            return quote!(());
+        }
+        if Service::is_raw(operation) {
+            return quote!(hyper::Response<hyper::Body>);
+        }
+        let ty = match Response::success(&operation.responses)
+            .first()
+            .map(|response| &response.body)
+        {
+            Some(Body::Events(_)) | Some(Body::Lines(_)) | Some(Body::Records(_))
+                if !operation.is_enumeration() =>
+            {
+                quote!(hyper::Body)
+            }
+            _ => operation.response_type(),
+        };
+        match Service::headers_type(operation) {
+            Some(headers) => quote!(ApiResponse<$ty, $headers>),
+            None => ty,
+        }
+    }
+
+    fn body_type(operation: &Operation) -> Tokens {
+        if Service::is_multipart(operation) {
+            quote!(hyper::Body)
+        } else {
+            operation.body_type()
+        }
+    }
+
+    fn arguments(operation: &Operation) -> Tokens {
+        quote!(
+            $(for parameter in operation.parameters.iter().chain(&operation.query) {
+                , $(&parameter.name):
+                $(if parameter.required {
+                    $(&parameter.ty)
+                } else {
+                    Option<$(&parameter.ty)>
+                })
+            })
+            $(if !operation.request.is_empty() {
+                , body:
+                $(if operation.request_required {
+                    $(Service::body_type(operation))
+                } else {
+                    Option<$(Service::body_type(operation))>
+                })
+            })
+        )
+    }
+
+    fn names(operation: &Operation) -> Vec<String> {
+        let mut names = operation
+            .parameters
+            .iter()
+            .chain(&operation.query)
+            .map(|parameter| parameter.name.clone())
+            .collect::<Vec<_>>();
+        if !operation.request.is_empty() {
+            names.push("body".to_string());
+        }
+        names
+    }
+
+    fn method_tokens(operation: &Operation) -> Tokens {
+        let names = Service::names(operation);
+        let unused = match names.len() {
+            0 => Tokens::new(),
+            1 => quote!(let _ = $(&names[0]);),
+            _ => quote!(let _ = ($(for name in &names join (, ) => $name));),
+        };
+        quote!(
+            $(operation.cfg())
+            #[doc = $(quoted(&operation.description))]
+            fn $(&operation.name)<'a>(&'a self $(Service::arguments(operation))) -> BoxFuture<'a, Result<$(Service::response_type(operation)), ServiceError>> {
+                $unused
+                Box::pin(async { Err(ServiceError::NotImplemented) })
+            }
+        )
+    }
+
+    fn decode_tokens(content: &Content) -> Tokens {
+        match content {
+            Content::Json(ty) => {
+                quote!(decode::<$ty>(&bytes).map_err(ServiceError::bad_request)?)
+            }
+            Content::Form(ty) => {
+                quote!(serde_urlencoded::from_bytes::<$ty>(&bytes).map_err(ServiceError::bad_request)?)
+            }
+            Content::Multipart | Content::Binary { .. } => quote!(hyper::Body::from(bytes)),
+        }
+    }
+
+    fn body_tokens(operation: &Operation) -> Tokens {
+        if operation.request.is_empty() {
+            return Tokens::new();
+        }
+        if Service::is_multipart(operation)
+            || (operation.request.len() == 1
+                && matches!(operation.request[0], Content::Binary { .. }))
+        {
+            return quote!(
+                let body = $(if operation.request_required {
+                    request_body
+                } else {
+                    Some(request_body)
+                });
+            );
+        }
+        let mut contents = operation.request.clone();
+        contents.sort_by_key(|content| matches!(content, Content::Binary { .. }));
+        let binaries = contents
+            .iter()
+            .filter(|content| matches!(content, Content::Binary { .. }))
+            .count();
+        let condition = |content: &Content| match content {
+            Content::Json(_) => quote!(content_type.contains("json")),
+            Content::Form(_) => quote!(content_type.contains("x-www-form-urlencoded")),
+            Content::Binary { media_type, .. } if binaries > 1 => {
+                quote!(content_type.starts_with($(quoted(media_type))))
+            }
+            _ => quote!(true),
+        };
+        let body_type = operation.body_type();
+        quote!(
+            $(if operation.request.len() > 1 {
+                let content_type = request_headers
+                    .get(CONTENT_TYPE)
+                    .and_then(|value| value.to_str().ok())
+                    .unwrap_or_default()
+                    .to_string();
+            })
+            let bytes = hyper::body::to_bytes(request_body)
+                .await
+                .map_err(ServiceError::bad_request)?;
+            let body = if bytes.is_empty() {
+                None
+            }
+            $(if operation.request.len() == 1 {
+                else {
+                    Some($(Service::decode_tokens(&operation.request[0])))
+                };
+            } else {
+                $(for content in &contents =>
+                    else if $(condition(content)) {
+                        Some($(&body_type)::$(content.variant())($(Service::decode_tokens(content))))
+                    }
+                )
+                else {
+                    return Err(ServiceError::Status(StatusCode::UNSUPPORTED_MEDIA_TYPE, None));
+                };
+            })
+            $(if operation.request_required {
+                let body = body.ok_or_else(|| ServiceError::bad_request("missing request body"))?;
+            })
+        )
+    }
+
+    fn reply_tokens(status: &Status, body: &Body) -> Tokens {
+        let status = Service::status(status);
+        match body {
+            Body::Empty => quote!(Ok(reply($status, None, hyper::Body::empty()))),
+            Body::Json(_) => quote!(json_reply($status, &body)),
+            Body::Text => quote!(Ok(reply($status, Some("text/plain"), body.into()))),
+            Body::Binary => quote!(Ok(reply($status, Some("application/octet-stream"), body))),
+            Body::Events(_) => quote!(Ok(reply($status, Some("text/event-stream"), body))),
+            Body::Lines(_) => quote!(Ok(reply($status, Some("application/x-ndjson"), body))),
+            Body::Records(_) => quote!(Ok(reply($status, Some("application/json-seq"), body))),
+        }
+    }
+
+    fn dispatch_tokens(operation: &Operation) -> Tokens {
+        let success = Response::success(&operation.responses);
+        let call = quote!(
+            self.service.$(&operation.name)($(for name in Service::names(operation) join (, ) => $name)).await?
+        );
+        let typed = Service::headers_type(operation).is_some();
+        let (headers, call) = if typed {
+            let headers = quote!(
+                let response = $call;
+                let mut headers = response.headers;
+                $(for header in operation.headers() =>
+                    $(if header.required {
+                        insert_header(&mut headers, $(quoted(&header.name)), &response.typed_headers.$(&header.field))?;
+                    } else {
+                        if let Some(value) = &response.typed_headers.$(&header.field) {
+                            insert_header(&mut headers, $(quoted(&header.name)), value)?;
+                        }
+                    })
+                )
+            );
+            (headers, quote!(response.body))
+        } else {
+            (Tokens::new(), call)
+        };
+        let discard = if typed {
+            Tokens::new()
+        } else {
+            quote!($(&call);)
+        };
+        let reply = if Service::is_raw(operation) {
+            quote!(Ok($call))
+        } else if operation.is_metadata() {
+            let status = success
+                .first()
+                .map(|response| Service::status(&response.status))
+                .unwrap_or_else(|| "200".to_string());
+            quote!(
+                $call;
+                Ok(reply($status, None, hyper::Body::empty()))
+            )
+        } else if operation.is_enumeration() {
+            let response_type = operation.response_type();
+            quote!(
+                match $call {
+                    $(for response in &success =>
+                        $(&response_type)::$(response.status.variant())
+                        $(if response.body != Body::Empty { (body) })
+                        => $(Service::reply_tokens(&response.status, &response.body)),
+                    )
+                }
+            )
+        } else {
+            match success.first() {
+                Some(response) if response.body == Body::Empty => quote!(
+                    $discard
+                    $(Service::reply_tokens(&response.status, &response.body))
+                ),
+                Some(response) => quote!(
+                    let body = $call;
+                    $(Service::reply_tokens(&response.status, &response.body))
+                ),
+                None => quote!(
+                    $discard
+                    Ok(reply(204, None, hyper::Body::empty()))
+                ),
+            }
+        };
+        let reply = if typed {
+            quote!(
+                $headers
+                with_headers({ $reply }, headers)
+            )
+        } else {
+            reply
+        };
+        quote!(
+            $(operation.cfg())
+            $(quoted(&operation.id)) => {
+                $(for parameter in &operation.parameters =>
+                    let $(&parameter.name) = path_parameter::<$(&parameter.ty)>(request_parameters, $(quoted(&parameter.original_name)))
+                        .map_err(ServiceError::bad_request)?;
+                    $(if !parameter.required {
+                        let $(&parameter.name) = Some($(&parameter.name));
+                    })
+                )
+                $(for parameter in &operation.query =>
+                    let $(&parameter.name) = query_parameter::<$(&parameter.ty)>(request_query, $(quoted(&parameter.original_name)))
+                        .map_err(ServiceError::bad_request)?;
+                    $(if parameter.required {
+                        let $(&parameter.name) = $(&parameter.name).ok_or_else(|| {
+                            ServiceError::bad_request($(quoted(format!("missing query parameter {}", parameter.original_name))))
+                        })?;
+                    })
+                )
+                $(Service::body_tokens(operation))
+                $reply
+            }
+        )
+    }
+
+    pub fn tokens() -> Tokens {
+        let operations = Operation::all();
+        let typed = operations
+            .iter()
+            .any(|operation| Service::headers_type(operation).is_some());
+        let mut errors = BTreeSet::new();
+        for operation in operations.iter() {
+            for response in Response::errors(&operation.responses) {
+                if let Body::Json(ty) = response.body {
+                    errors.insert(ty);
+                }
+            }
+        }
+        let model_cfg = |ty: &String| match Model::get(ty) {
+            Some(model) => cfg(&model.features),
+            None => Tokens::new(),
+        };
+        let uses = |used: bool, name: &str| match used {
+            true => name.to_string(),
+            false => format!("_{name}"),
+        };
+        let parameters = uses(
+            operations
+                .iter()
+                .any(|operation| !operation.parameters.is_empty()),
+            "request_parameters",
+        );
+        let query = uses(
+            operations
+                .iter()
+                .any(|operation| !operation.query.is_empty()),
+            "request_query",
+        );
+        let headers = uses(
+            operations
+                .iter()
+                .any(|operation| operation.request.len() > 1 && !Service::is_multipart(operation)),
+            "request_headers",
+        );
+        let body = uses(
+            operations
+                .iter()
+                .any(|operation| !operation.request.is_empty()),
+            "request_body",
+        );
+        quote!(
+            use super::*;
+            use futures_util::future::BoxFuture;
+            use hyper::{
+                header::CONTENT_TYPE,
+                service::{make_service_fn, service_fn},
+                HeaderMap, Method, StatusCode,
+            };
+            use serde::{de::DeserializeOwned, Serialize};
+            use serde_json::Value;
+            use std::{collections::HashMap, convert::Infallible, net::TcpListener, sync::Arc};
+            $['\n']
+            #[doc = "An error answered by `Router` in place of an operation's result"]
+            #[derive(Debug)]
+            pub enum ServiceError {
+                #[doc = "One of the error responses documented by the spec"]
+                Api(ApiError),
+                #[doc = "Any status, with an optional JSON body"]
+                Status(StatusCode, Option<Value>),
+                #[doc = "Answered as 501 by operations the service does not implement"]
+                NotImplemented,
+            }
+            $['\n']
+            impl ServiceError {
+                pub fn bad_request<M: std::fmt::Display>(message: M) -> Self {
+                    ServiceError::Status(
+                        StatusCode::BAD_REQUEST,
+                        Some(serde_json::json!({ "error": message.to_string() })),
+                    )
+                }
+            }
+            $['\n']
+            impl From<ApiError> for ServiceError {
+                fn from(error: ApiError) -> Self {
+                    ServiceError::Api(error)
+                }
+            }
+            $['\n']
+            impl From<ServiceError> for hyper::Response<hyper::Body> {
+                fn from(error: ServiceError) -> Self {
+                    match error {
+                        $(if errors.is_empty() {
+                            ServiceError::Api(error) => match error {},
+                        } else {
+                            ServiceError::Api(error) => {
+                                let (status, headers, body) = match error {
+                                    $(for ty in &errors =>
+                                        $(model_cfg(ty))
+                                        ApiError::$ty(response) => (response.status, response.headers, serde_json::to_vec(&response.body)),
+                                    )
+                                };
+                                let mut response = reply(status.as_u16(), Some("application/json"), body.unwrap_or_default().into());
+                                for (name, value) in headers.iter() {
+                                    if name != CONTENT_TYPE {
+                                        response.headers_mut().insert(name, value.clone());
+                                    }
+                                }
+                                response
+                            }
+                        })
+                        ServiceError::Status(status, Some(body)) => {
+                            reply(status.as_u16(), Some("application/json"), body.to_string().into())
+                        }
+                        ServiceError::Status(status, None) => reply(status.as_u16(), None, hyper::Body::empty()),
+                        ServiceError::NotImplemented => reply(501, None, hyper::Body::empty()),
+                    }
+                }
+            }
+            $['\n']
+            #[doc = "The server side of the API, one method per operation; unimplemented operations answer 501"]
+            pub trait ApiService: Send + Sync + 'static {
+                $(for operation in &operations =>
+                    $(Service::method_tokens(operation))
+                    $['\n']
+                )
+            }
+            $['\n']
+            #[doc = "Routes requests to an `ApiService`, extracting parameters and bodies into the generated types"]
+            pub struct Router<S> {
+                service: Arc<S>,
+            }
+            $['\n']
+            impl<S> Clone for Router<S> {
+                fn clone(&self) -> Self {
+                    Self {
+                        service: self.service.clone(),
+                    }
+                }
+            }
+            $['\n']
+            impl<S: ApiService> Router<S> {
+                pub fn new(service: S) -> Self {
+                    Self {
+                        service: Arc::new(service),
+                    }
+                }
+                $['\n']
+                pub async fn handle(&self, request: hyper::Request<hyper::Body>) -> hyper::Response<hyper::Body> {
+                    let (parts, body) = request.into_parts();
+                    let (operation, parameters) = match route(&parts.method, parts.uri.path()) {
+                        Some(route) => route,
+                        None => return ServiceError::Status(StatusCode::NOT_FOUND, None).into(),
+                    };
+                    let query = match serde_urlencoded::from_str::<Vec<(String, String)>>(parts.uri.query().unwrap_or_default()) {
+                        Ok(query) => query,
+                        Err(error) => return ServiceError::bad_request(error).into(),
+                    };
+                    match self.dispatch(operation, &parameters, &query, &parts.headers, body).await {
+                        Ok(response) => response,
+                        Err(error) => error.into(),
+                    }
+                }
+                $['\n']
+                #[doc = "Serves connections accepted on `listener` until the returned future is dropped"]
+                pub async fn serve(self, listener: TcpListener) -> Result<(), Error> {
+                    let service = make_service_fn(move |_| {
+                        let router = self.clone();
+                        async move {
+                            Ok::<_, Infallible>(service_fn(move |request| {
+                                let router = router.clone();
+                                async move { Ok::<_, Infallible>(router.handle(request).await) }
+                            }))
+                        }
+                    });
+                    hyper::Server::from_tcp(listener)?.serve(service).await?;
+                    Ok(())
+                }
+                $['\n']
+                async fn dispatch(
+                    &self,
+                    operation: &str,
+                    $parameters: &HashMap<String, String>,
+                    $query: &[(String, String)],
+                    $headers: &HeaderMap,
+                    $body: hyper::Body,
+                ) -> Result<hyper::Response<hyper::Body>, ServiceError> {
+                    match operation {
+                        $(for operation in &operations =>
+                            $(Service::dispatch_tokens(operation))
+                        )
+                        _ => Err(ServiceError::Status(StatusCode::NOT_FOUND, None)),
+                    }
+                }
+            }
+            $['\n']
+            $(Operation::route_tokens())
+            $['\n']
+            fn reply(status: u16, content_type: Option<&'static str>, body: hyper::Body) -> hyper::Response<hyper::Body> {
+                let mut response = hyper::Response::new(body);
+                *response.status_mut() = StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+                if let Some(content_type) = content_type {
+                    response.headers_mut().insert(CONTENT_TYPE, hyper::header::HeaderValue::from_static(content_type));
+                }
+                response
+            }
+            $(if typed {
+                $['\n']
+                fn insert_header<T: std::fmt::Display>(headers: &mut HeaderMap, name: &str, value: &T) -> Result<(), ServiceError> {
+                    let invalid = |error: String| {
+                        ServiceError::Status(
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            Some(serde_json::json!({ "error": error })),
+                        )
+                    };
+                    let name = hyper::header::HeaderName::from_bytes(name.as_bytes()).map_err(|error| invalid(error.to_string()))?;
+                    let value = hyper::header::HeaderValue::from_str(&value.to_string()).map_err(|error| invalid(error.to_string()))?;
+                    headers.insert(name, value);
+                    Ok(())
+                }
+                $['\n']
+                fn with_headers(
+                    response: Result<hyper::Response<hyper::Body>, ServiceError>,
+                    headers: HeaderMap,
+                ) -> Result<hyper::Response<hyper::Body>, ServiceError> {
+                    let mut response = response?;
+                    for (name, value) in headers.iter() {
+                        if name != CONTENT_TYPE {
+                            response.headers_mut().insert(name, value.clone());
+                        }
+                    }
+                    Ok(response)
+                }
+            })
+            $['\n']
+            fn json_reply<T: Serialize>(status: u16, body: &T) -> Result<hyper::Response<hyper::Body>, ServiceError> {
+                match serde_json::to_vec(body) {
+                    Ok(body) => Ok(reply(status, Some("application/json"), body.into())),
+                    Err(error) => Err(ServiceError::Status(
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Some(serde_json::json!({ "error": error.to_string() })),
+                    )),
+                }
+            }
+        )
+    }
+}
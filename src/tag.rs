@@ -0,0 +1,199 @@
+use crate::prelude::*;
+use once_cell::sync::OnceCell;
+use std::{collections::BTreeMap, sync::Mutex};
+
+static TAGS: OnceCell<Mutex<BTreeMap<String, Tag>>> = OnceCell::new();
+
+#[derive(Clone)]
+pub struct Tag {
+    pub name: String,
+    pub description: Option<String>,
+}
+
+impl Tag {
+    pub fn all() -> Vec<Tag> {
+        let operations = Operation::all();
+        TAGS.get_or_init(|| Mutex::new(BTreeMap::new()))
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|tag| {
+                operations
+                    .iter()
+                    .any(|operation| operation.tag.as_ref() == Some(&tag.name))
+            })
+            .cloned()
+            .collect()
+    }
+
+    fn add(tag: Tag) {
+        let mut tags = TAGS
+            .get_or_init(|| Mutex::new(BTreeMap::new()))
+            .lock()
+            .unwrap();
+        if tags.contains_key(&tag.name) {
+            panic!("Tag {} already exists", tag.name);
+        }
+        tags.insert(tag.name.clone(), tag);
+    }
+
+    pub fn get(name: &str) -> Option<Tag> {
+        TAGS.get_or_init(|| Mutex::new(BTreeMap::new()))
+            .lock()
+            .unwrap()
+            .get(name)
+            .cloned()
+    }
+
+    pub fn discover(name: &str, description: Option<String>) -> Tag {
+        match Tag::get(name) {
+            Some(tag) => tag,
+            None => {
+                let tag = Tag {
+                    name: name.to_string(),
+                    description,
+                };
+                Tag::add(tag.clone());
+                tag
+            }
+        }
+    }
+
+    pub fn module(&self) -> String {
+        let module = self.name.to_snake_case();
+        if RESERVED_MODULES.contains(&module.as_str()) || KEYWORDS.contains(&module.as_str()) {
+            format!("{module}_tag")
+        } else {
+            module
+        }
+    }
+
+    pub fn feature(&self) -> Option<String> {
+        Config::get().package.as_ref().map(|_| {
+            let module = self.module();
+            if RESERVED_FEATURES.contains(&module.as_str()) {
+                format!("{module}_tag")
+            } else {
+                module
+            }
+        })
+    }
+
+    pub fn client(&self) -> String {
+        format!("{}_client", self.name).to_upper_camel_case()
+    }
+
+    pub fn operations(&self) -> Vec<Operation> {
+        Operation::all()
+            .into_iter()
+            .filter(|operation| operation.tag.as_ref() == Some(&self.name))
+            .collect()
+    }
+
+    fn method_names(&self, operations: &[Operation]) -> Vec<String> {
+        let words = self.name.to_snake_case();
+        let words = words.split('_').collect::<Vec<_>>();
+        let mut singular = words.clone();
+        if let Some(last) = singular.last_mut() {
+            let word: &str = last;
+            *last = word.strip_suffix('s').unwrap_or(word);
+        }
+        let names = operations
+            .iter()
+            .map(|operation| {
+                let name = operation.name.split('_').collect::<Vec<_>>();
+                for words in [&words, &singular] {
+                    if let Some(index) = name
+                        .windows(words.len())
+                        .position(|window| window == words.as_slice())
+                    {
+                        let mut short = name.clone();
+                        short.drain(index..index + words.len());
+                        let short = short.join("_");
+                        if !short.is_empty() && !KEYWORDS.contains(&short.as_str()) {
+                            return short;
+                        }
+                    }
+                }
+                operation.name.clone()
+            })
+            .collect::<Vec<_>>();
+        names
+            .iter()
+            .zip(operations)
+            .map(|(name, operation)| {
+                if names.iter().filter(|other| *other == name).count() > 1 {
+                    operation.name.clone()
+                } else {
+                    name.clone()
+                }
+            })
+            .collect()
+    }
+
+    pub fn blocking_tokens(&self) -> Tokens {
+        let client = self.client();
+        let module = self.module();
+        let cfg = cfg(&self.feature().into_iter().collect());
+        let operations = self.operations();
+        let names = self.method_names(&operations);
+        quote!(
+            $(&cfg)
+            $(self.description.as_ref().map(|description| quote!(#[doc = $(quoted(description))])))
+            pub struct $(&client)<'a> {
+                api: &'a Api,
+            }
+            $['\n']
+            $(&cfg)
+            impl Api {
+                pub fn $(&module)(&self) -> $(&client)<'_> {
+                    $(&client) { api: self }
+                }
+            }
+            $['\n']
+            $(&cfg)
+            impl<'a> $(&client)<'a> {
+                $(for (operation, name) in operations.iter().zip(&names) =>
+                    $(operation.blocking_tokens(name, quote!(self.api), quote!(.$(&module)())))
+                    $['\n']
+                )
+            }
+        )
+    }
+
+    pub fn tokens(&self) -> Result<Tokens, Error> {
+        let client = self.client();
+        let operations = self.operations();
+        let names = self.method_names(&operations);
+        let tokens = quote!(
+            $(self.description.as_ref().map(|description| quote!(#[doc = $(quoted(description))])))
+            pub struct $(&client)<'a> {
+                api: &'a Api,
+            }
+            $['\n']
+            impl Api {
+                pub fn $(self.module())(&self) -> $(&client)<'_> {
+                    $(&client) { api: self }
+                }
+            }
+            $['\n']
+            impl<'a> $(&client)<'a> {
+                $(for (operation, name) in operations.iter().zip(&names) =>
+                    $(operation.tokens(name, quote!(self.api))?)
+                    $['\n']
+                    $(operation.stream_tokens(name, quote!(self.api), quote!('a))?)
+                    $['\n']
+                )
+            }
+        );
+        Ok(quote!(
+            use super::*;
+            use hyper::Method;
+            $(if identifiers(&tokens)?.contains("Value") {
+                use serde_json::Value;
+            })
+            $['\n']
+            $tokens
+        ))
+    }
+}
@@ -3,6 +3,7 @@
 mod cycle;
 
 use cycle::*;
+use futures_util::StreamExt;
 use serde_json::json;
 
 #[test]
@@ -16,10 +17,10 @@ fn test_enum_serialization() {
 
 #[tokio::test]
 async fn test_resource_operation() {
-    let api = Api::new(
-        std::env::var("CYCLE_KEY").unwrap(),
-        std::env::var("CYCLE_HUB").unwrap(),
-    );
+    let mock = mock::MockServer::start().await.unwrap();
+    let api = Api::builder(Credentials::default().bearer_auth("key").hub_auth("hub"))
+        .endpoint(mock.url())
+        .build();
     let request = CreateEnvironmentRequest::new(
         "test",
         "test",
@@ -41,19 +42,271 @@ async fn test_resource_operation() {
             "stack": null,
         })
     );
-    // api.create_environment(request).await.unwrap();
-    match api.create_environment(request).await {
-        Ok(_) => {}
-        Err(e) => {
-            println!("Error: {e:#?}");
-        }
-    }
+    api.environments().create(Some(request)).await.unwrap();
     let body = api
-        .get_environments(None, None, None, Some(vec!["test".to_string()]), None)
+        .environments()
+        .get(None, None, None, Some(vec!["test".to_string()]), None)
         .await
         .unwrap();
     assert_eq!(body.data.len(), 1);
-    api.remove_environment(body.data[0].id.clone())
+    api.environments()
+        .remove(body.data[0].id.clone())
+        .await
+        .unwrap();
+    let requests = mock.requests();
+    assert_eq!(
+        requests
+            .iter()
+            .map(|request| request.operation)
+            .collect::<Vec<_>>(),
+        ["createEnvironment", "getEnvironments", "removeEnvironment"]
+    );
+    assert_eq!(
+        requests[0].json::<CreateEnvironmentRequest>().unwrap().name,
+        "test"
+    );
+    assert_eq!(requests[2].parameters["environmentId"], body.data[0].id);
+}
+
+async fn stream_pages<F>(ids: F) -> (Vec<String>, Vec<String>)
+where
+    F: Fn(u64) -> Vec<String> + Send + Sync + 'static,
+{
+    let mock = mock::MockServer::start().await.unwrap();
+    let api = Api::builder(Credentials::default().bearer_auth("key").hub_auth("hub"))
+        .endpoint(mock.url())
+        .build();
+    let environment = api
+        .environments()
+        .get(None, None, None, None, None)
+        .await
+        .unwrap()
+        .data
+        .remove(0);
+    mock.reset();
+    mock.stub("getEnvironments", move |request| {
+        let page = request
+            .query
+            .iter()
+            .find(|(key, _)| key == "page[number]")
+            .and_then(|(_, value)| value.parse().ok())
+            .unwrap_or_default();
+        let data = ids(page)
+            .into_iter()
+            .map(|id| {
+                let mut environment = environment.clone();
+                environment.id = id;
+                environment
+            })
+            .collect::<Vec<_>>();
+        mock::MockResponse::json(hyper::StatusCode::OK, &json!({ "data": data }))
+    });
+    let ids = api
+        .environments()
+        .get_stream(None, None, None, None, None)
+        .map(|environment| environment.unwrap().id)
+        .collect::<Vec<_>>()
+        .await;
+    let pages = mock
+        .requests()
+        .iter()
+        .map(|request| {
+            request
+                .query
+                .iter()
+                .find(|(key, _)| key == "page[number]")
+                .map(|(_, value)| value.clone())
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>();
+    (ids, pages)
+}
+
+#[tokio::test]
+async fn test_stream() {
+    let (ids, pages) = stream_pages(|page| match page {
+        1 => vec!["a".to_string(), "b".to_string()],
+        2 => vec!["c".to_string(), "d".to_string()],
+        _ => vec![],
+    })
+    .await;
+    assert_eq!(ids, ["a", "b", "c", "d"]);
+    assert_eq!(pages, ["1", "2", "3"]);
+}
+
+#[tokio::test]
+async fn test_stream_short_page() {
+    let (ids, pages) = stream_pages(|page| match page {
+        1 => vec!["a".to_string(), "b".to_string()],
+        _ => vec!["c".to_string()],
+    })
+    .await;
+    assert_eq!(ids, ["a", "b", "c"]);
+    assert_eq!(pages, ["1", "2"]);
+}
+
+#[tokio::test]
+async fn test_stream_repeated_page() {
+    let (ids, pages) = stream_pages(|_| vec!["a".to_string(), "b".to_string()]).await;
+    assert_eq!(ids, ["a", "b"]);
+    assert_eq!(pages, ["1", "2"]);
+}
+
+#[tokio::test]
+async fn test_stream_max_pages() {
+    let (ids, pages) = stream_pages(|page| vec![format!("{page}a"), format!("{page}b")]).await;
+    assert_eq!(ids.len(), 10);
+    assert_eq!(ids.last().unwrap(), "5b");
+    assert_eq!(pages, ["1", "2", "3", "4", "5"]);
+}
+
+#[tokio::test]
+async fn test_default_headers() {
+    let mock = mock::MockServer::start().await.unwrap();
+    let api = Api::builder(Credentials::default().bearer_auth("key").hub_auth("hub"))
+        .endpoint(mock.url())
+        .user_agent("agent")
+        .header(
+            hyper::header::AUTHORIZATION,
+            hyper::header::HeaderValue::from_static("Bearer default"),
+        )
+        .header(
+            hyper::header::CONTENT_TYPE,
+            hyper::header::HeaderValue::from_static("text/plain"),
+        )
+        .header(
+            hyper::header::USER_AGENT,
+            hyper::header::HeaderValue::from_static("default"),
+        )
+        .header(
+            hyper::header::HeaderName::from_static("x-trace"),
+            hyper::header::HeaderValue::from_static("trace"),
+        )
+        .build();
+    let request = CreateEnvironmentRequest::new(
+        "test",
+        "test",
+        CreateEnvironmentRequestAbout::new("test"),
+        Features::new(false),
+        None,
+    );
+    api.environments().create(Some(request)).await.unwrap();
+    let headers = &mock.requests()[0].headers;
+    let values = |name: &str| headers.get_all(name).iter().collect::<Vec<_>>();
+    assert_eq!(values("authorization"), ["Bearer key"]);
+    assert_eq!(values("content-type"), ["application/json"]);
+    assert_eq!(values("user-agent"), ["agent"]);
+    assert_eq!(values("x-trace"), ["trace"]);
+}
+
+#[tokio::test]
+async fn test_retry() {
+    let mock = mock::MockServer::start().await.unwrap();
+    let api = Api::builder(Credentials::default().bearer_auth("key").hub_auth("hub"))
+        .endpoint(mock.url())
+        .build();
+    let unavailable = || {
+        mock::MockResponse::new(hyper::StatusCode::SERVICE_UNAVAILABLE).header(
+            hyper::header::RETRY_AFTER,
+            hyper::header::HeaderValue::from_static("0"),
+        )
+    };
+    let attempts = std::sync::atomic::AtomicUsize::new(0);
+    mock.stub("getEnvironments", move |_| {
+        match attempts.fetch_add(1, std::sync::atomic::Ordering::SeqCst) {
+            0 => unavailable(),
+            _ => mock::MockResponse::json(hyper::StatusCode::OK, &json!({ "data": [] })),
+        }
+    });
+    let body = api
+        .environments()
+        .get(None, None, None, None, None)
         .await
         .unwrap();
+    assert!(body.data.is_empty());
+    assert_eq!(mock.requests().len(), 2);
+    mock.reset();
+    mock.stub("createEnvironment", move |_| unavailable());
+    let request = CreateEnvironmentRequest::new(
+        "test",
+        "test",
+        CreateEnvironmentRequestAbout::new("test"),
+        Features::new(false),
+        None,
+    );
+    api.environments().create(Some(request)).await.unwrap_err();
+    assert_eq!(mock.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn test_mock_stub() {
+    let mock = mock::MockServer::start().await.unwrap();
+    let api = Api::builder(Credentials::default().bearer_auth("key").hub_auth("hub"))
+        .endpoint(mock.url())
+        .build();
+    mock.stub("getEnvironments", |_| {
+        mock::MockResponse::json(hyper::StatusCode::OK, &json!({ "data": [] }))
+    });
+    let body = api
+        .environments()
+        .get(None, None, None, None, None)
+        .await
+        .unwrap();
+    assert!(body.data.is_empty());
+    let request = hyper::Request::post(format!("{}/v1/environments", mock.url()))
+        .header("content-type", "application/json")
+        .body(hyper::Body::from(r#"{"name": 1}"#))
+        .unwrap();
+    let response = hyper::Client::new().request(request).await.unwrap();
+    assert_eq!(response.status(), hyper::StatusCode::BAD_REQUEST);
+}
+
+struct Environments;
+
+impl service::ApiService for Environments {
+    fn get_environments<'a>(
+        &'a self,
+        _meta: Option<GetEnvironmentsMeta>,
+        _include: Option<GetEnvironmentsInclude>,
+        _filter: Option<GetEnvironmentsFilter>,
+        sort: Option<Vec<String>>,
+        _page: Option<PageParam>,
+    ) -> futures_util::future::BoxFuture<'a, Result<GetEnvironmentsResponse, service::ServiceError>>
+    {
+        Box::pin(async move {
+            match sort {
+                Some(sort) if sort == ["name", "state"] => Ok(GetEnvironmentsResponse::new(vec![])),
+                _ => Err(service::ServiceError::bad_request("unexpected sort")),
+            }
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_service() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let server = tokio::spawn(service::Router::new(Environments).serve(listener));
+    let api = Api::builder(Credentials::default().bearer_auth("key").hub_auth("hub"))
+        .endpoint(format!("http://{address}"))
+        .build();
+    let body = api
+        .environments()
+        .get(
+            None,
+            None,
+            None,
+            Some(vec!["name".to_string(), "state".to_string()]),
+            None,
+        )
+        .await
+        .unwrap();
+    assert!(body.data.is_empty());
+    let error = api
+        .environments()
+        .remove("environment".to_string())
+        .await
+        .unwrap_err();
+    assert_eq!(error.status(), Some(hyper::StatusCode::NOT_IMPLEMENTED));
+    server.abort();
 }
@@ -0,0 +1,349 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+struct Generated {
+    dir: PathBuf,
+    stderr: String,
+}
+
+impl Generated {
+    fn file(&self, path: &str) -> String {
+        fs::read_to_string(self.dir.join(path)).unwrap()
+    }
+
+    fn source(&self) -> String {
+        fn collect(dir: &Path, source: &mut String) {
+            let mut entries: Vec<_> = fs::read_dir(dir)
+                .unwrap()
+                .map(|entry| entry.unwrap().path())
+                .collect();
+            entries.sort();
+            for path in entries {
+                if path.is_dir() {
+                    collect(&path, source);
+                } else if path.extension().map_or(false, |ext| ext == "rs") {
+                    source.push_str(&fs::read_to_string(path).unwrap());
+                }
+            }
+        }
+        let mut source = String::new();
+        collect(&self.dir, &mut source);
+        source
+    }
+}
+
+fn spec(body: &str) -> String {
+    format!(
+        "openapi: 3.0.3\n\
+         info:\n  title: Generated\n  version: 0.1.0\n\
+         servers:\n  - url: http://localhost:8080\n\
+         {body}"
+    )
+}
+
+fn generate(name: &str, spec: &str, config: &str) -> Generated {
+    let dir = std::env::temp_dir().join(format!("colmenar-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("spec.yaml"), spec).unwrap();
+    fs::write(dir.join("config.yaml"), config).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_colmenar"))
+        .arg(dir.join("spec.yaml"))
+        .arg(dir.join("out"))
+        .arg(dir.join("config.yaml"))
+        .output()
+        .unwrap();
+    Generated {
+        dir: dir.join("out"),
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+    }
+}
+
+const INSTANCES: &str = r#"
+paths:
+  /v1/environments:
+    get:
+      responses:
+        '204':
+          description: Listed.
+  /v1/environments/{environmentId}/instances:
+    get:
+      parameters:
+        - name: environmentId
+          in: path
+          required: true
+          schema:
+            type: string
+      responses:
+        '204':
+          description: Listed.
+    post:
+      operationId: createInstance
+      parameters:
+        - name: environmentId
+          in: path
+          required: true
+          schema:
+            type: string
+      responses:
+        '204':
+          description: Created.
+components: {}
+"#;
+
+#[test]
+fn test_synthesized_names() {
+    let generated = generate("synthesized", &spec(INSTANCES), "{}");
+    assert_eq!(generated.stderr, "");
+    let source = generated.source();
+    assert!(source.contains("fn get_environments("));
+    assert!(source.contains("fn get_environments_by_environment_id_instances("));
+    assert!(source.contains("fn create_instance("));
+}
+
+#[test]
+fn test_renames() {
+    let config = r#"
+rename:
+  GET /v1/environments/{environmentId}/instances: listInstances
+  createInstance: startInstance
+"#;
+    let generated = generate("renames", &spec(INSTANCES), config);
+    assert_eq!(generated.stderr, "");
+    let source = generated.source();
+    assert!(source.contains("fn list_instances("));
+    assert!(source.contains("fn start_instance("));
+    assert!(!source.contains("fn get_environments_by_environment_id_instances("));
+    assert!(!source.contains("fn create_instance("));
+}
+
+#[test]
+fn test_name_collision() {
+    let paths = r#"
+paths:
+  /v1/environments:
+    get:
+      responses:
+        '204':
+          description: Listed.
+    post:
+      operationId: getEnvironments
+      responses:
+        '204':
+          description: Created.
+components: {}
+"#;
+    let generated = generate("collision", &spec(paths), "{}");
+    assert!(generated.stderr.contains(
+        "Operation name 'get_environments' for POST /v1/environments collides with GET /v1/environments"
+    ));
+
+    let config = "rename:\n  POST /v1/environments: createEnvironment\n";
+    let generated = generate("collision-renamed", &spec(paths), config);
+    assert_eq!(generated.stderr, "");
+    assert!(generated.source().contains("fn create_environment("));
+}
+
+const LAYOUTS: &str = r#"
+tags:
+  - name: Billing
+paths:
+  /v1/environments/{environmentId}/invoices:
+    get:
+      tags:
+        - Billing
+      operationId: listInvoices
+      parameters:
+        - name: environmentId
+          in: path
+          required: true
+          schema:
+            type: string
+      responses:
+        '200':
+          description: Listed.
+          content:
+            application/json:
+              schema:
+                $ref: '#/components/schemas/ledger.Invoice'
+  /v1/environments/{environmentId}:
+    get:
+      operationId: getEnvironment
+      parameters:
+        - name: environmentId
+          in: path
+          required: true
+          schema:
+            type: string
+      responses:
+        '200':
+          description: Found.
+          content:
+            application/json:
+              schema:
+                $ref: '#/components/schemas/Environment'
+components:
+  schemas:
+    ledger.Invoice:
+      type: object
+      properties:
+        total:
+          type: integer
+    Environment:
+      type: object
+      properties:
+        name:
+          type: string
+"#;
+
+#[test]
+fn test_layouts() {
+    let generated = generate("layout-single", &spec(LAYOUTS), "layout: single\n");
+    assert_eq!(generated.stderr, "");
+    let models = generated.file("model.rs");
+    assert!(models.contains("pub struct LedgerInvoice"));
+    assert!(models.contains("pub struct Environment"));
+    assert!(generated.file("billing.rs").contains("fn list_invoices("));
+
+    let generated = generate("layout-models", &spec(LAYOUTS), "layout: models\n");
+    assert_eq!(generated.stderr, "");
+    assert!(generated
+        .file("model/mod.rs")
+        .contains("mod ledger_invoice;"));
+    assert!(generated
+        .file("model/ledger_invoice.rs")
+        .contains("pub struct LedgerInvoice"));
+    assert!(generated
+        .file("model/environment.rs")
+        .contains("pub struct Environment"));
+
+    let generated = generate("layout-tags", &spec(LAYOUTS), "layout: tags\n");
+    assert_eq!(generated.stderr, "");
+    assert!(generated
+        .file("billing/model.rs")
+        .contains("pub struct LedgerInvoice"));
+    assert!(generated
+        .file("billing/client.rs")
+        .contains("fn list_invoices("));
+    let models = generated.file("model.rs");
+    assert!(models.contains("pub struct Environment"));
+    assert!(!models.contains("pub struct LedgerInvoice"));
+
+    let generated = generate("layout-namespaces", &spec(LAYOUTS), "layout: namespaces\n");
+    assert_eq!(generated.stderr, "");
+    assert!(generated
+        .file("ledger.rs")
+        .contains("pub struct LedgerInvoice"));
+    assert!(!generated
+        .file("billing.rs")
+        .contains("pub struct LedgerInvoice"));
+    assert!(!generated
+        .file("model.rs")
+        .contains("pub struct LedgerInvoice"));
+}
+
+const PACKAGE: &str = r#"openapi: 3.0.3
+info:
+  title: Cycle Client Test
+  description: |
+    Manages environments.
+
+    Longer description.
+  version: v2.1
+  license:
+    name: Example Proprietary License
+servers:
+  - url: http://localhost:8080
+tags:
+  - name: Environments
+    description: Environment operations.
+paths:
+  /v1/environments:
+    get:
+      tags:
+        - Environments
+      operationId: getEnvironments
+      responses:
+        '204':
+          description: Listed.
+components: {}
+"#;
+
+#[test]
+fn test_package() {
+    let license = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/license.txt");
+    let config = format!("crate:\n  license: {license}\nmock: true\n");
+    let generated = generate("package", PACKAGE, &config);
+    assert_eq!(generated.stderr, "");
+    let manifest = generated.file("Cargo.toml");
+    assert!(manifest.starts_with(
+        "[package]\nname = \"cycle-client-test\"\nversion = \"2.1.0\"\nedition = \"2021\"\n\
+         description = \"Manages environments.\"\nlicense-file = \"LICENSE\"\n"
+    ));
+    assert!(manifest.contains("default = [\"full\", \"native-tls\"]\nfull = [\"environments\"]\n"));
+    assert!(manifest.contains("mock = [\"hyper/server\"]\n"));
+    assert!(manifest.contains("\n[dependencies]\n"));
+    assert_eq!(
+        generated.file("LICENSE"),
+        fs::read_to_string(license).unwrap()
+    );
+    let readme = generated.file("README.md");
+    assert!(readme.starts_with("# Cycle Client Test\n\nManages environments."));
+    assert!(readme.contains("- `environments`: Environment operations.\n"));
+    assert!(generated.file("src/lib.rs").contains("mod environments;"));
+
+    let spdx = PACKAGE.replace("Example Proprietary License", "MIT OR Apache-2.0");
+    let generated = generate("package-spdx", &spdx, "crate: {}\n");
+    assert_eq!(generated.stderr, "");
+    assert!(generated
+        .file("Cargo.toml")
+        .contains("license = \"MIT OR Apache-2.0\"\n"));
+    assert!(!generated.dir.join("LICENSE").exists());
+}
+
+#[test]
+fn test_reserved_feature_names() {
+    let spec = PACKAGE
+        .replace("name: Environments", "name: Full")
+        .replace("- Environments", "- Full");
+    let generated = generate("package-features", &spec, "crate: {}\n");
+    assert_eq!(generated.stderr, "");
+    let manifest = generated.file("Cargo.toml");
+    assert!(manifest.contains("full = [\"full_tag\"]\nfull_tag = []\n"));
+    assert!(generated
+        .file("src/lib.rs")
+        .contains("#[cfg(any(feature = \"full_tag\"))]\npub mod full;"));
+}
+
+#[test]
+fn test_reserved_model_names() {
+    let paths = r#"
+paths:
+  /v1/environments:
+    get:
+      operationId: getServer
+      responses:
+        '200':
+          description: Found.
+          content:
+            application/json:
+              schema:
+                $ref: '#/components/schemas/Server'
+components:
+  schemas:
+    Server:
+      type: object
+      properties:
+        name:
+          type: string
+"#;
+    let generated = generate("reserved-models", &spec(paths), "{}");
+    assert_eq!(generated.stderr, "");
+    let source = generated.source();
+    assert!(source.contains("pub struct ServerModel"));
+    assert!(source.contains("pub enum Server {"));
+    assert!(source.contains("Result<ServerModel, Error>"));
+}
@@ -0,0 +1,404 @@
+#![allow(dead_code)]
+
+mod sample;
+
+use futures_util::{future::BoxFuture, FutureExt, StreamExt};
+use sample::*;
+use serde_json::{json, Value};
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+async fn start() -> (mock::MockServer, Api) {
+    let mock = mock::MockServer::start().await.unwrap();
+    let api = Api::builder(Credentials::default().bearer_auth("key"))
+        .endpoint(mock.url())
+        .build();
+    (mock, api)
+}
+
+fn chunked(chunks: &[&'static str]) -> hyper::Body {
+    let chunks = chunks.to_vec();
+    hyper::Body::wrap_stream(futures_util::stream::iter(
+        chunks.into_iter().map(Ok::<_, std::io::Error>),
+    ))
+}
+
+async fn items(stream: EventStream<Value>) -> Vec<Value> {
+    stream.map(Result::unwrap).collect().await
+}
+
+fn content_type(request: &mock::MockRequest) -> &str {
+    request
+        .headers
+        .get(hyper::header::CONTENT_TYPE)
+        .unwrap()
+        .to_str()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_multipart_body() {
+    let (mock, api) = start().await;
+    let body = Multipart::new().text("note", "hello").part(
+        "file",
+        Part::bytes("data")
+            .file_name("a\"b.txt")
+            .content_type("text/plain"),
+    );
+    api.upload_file("environment".to_string(), body)
+        .await
+        .unwrap();
+    let request = &mock.requests()[0];
+    assert_eq!(request.operation, "uploadFile");
+    let boundary = content_type(request)
+        .strip_prefix("multipart/form-data; boundary=")
+        .unwrap()
+        .to_string();
+    assert_eq!(
+        String::from_utf8_lossy(&request.body),
+        format!(
+            "--{boundary}\r\nContent-Disposition: form-data; name=\"note\"\r\n\r\nhello\r\n\
+             --{boundary}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"a%22b.txt\"\r\n\
+             Content-Type: text/plain\r\n\r\ndata\r\n--{boundary}--\r\n"
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_form_body() {
+    let (mock, api) = start().await;
+    let settings = Settings {
+        name: "web".to_string(),
+        replicas: Some(2),
+    };
+    api.update_settings("environment".to_string(), settings)
+        .await
+        .unwrap();
+    let request = &mock.requests()[0];
+    assert_eq!(request.operation, "updateSettings");
+    assert_eq!(content_type(request), "application/x-www-form-urlencoded");
+    assert_eq!(&request.body[..], b"name=web&replicas=2");
+}
+
+#[tokio::test]
+async fn test_binary_body() {
+    let (mock, api) = start().await;
+    api.upload_archive("environment".to_string(), hyper::Body::from("archive"))
+        .await
+        .unwrap();
+    let request = &mock.requests()[0];
+    assert_eq!(request.operation, "uploadArchive");
+    assert_eq!(content_type(request), "application/gzip");
+    assert_eq!(&request.body[..], b"archive");
+}
+
+#[tokio::test]
+async fn test_event_stream_framing() {
+    let body = chunked(&[
+        "data: {\"a\"",
+        ":1}\r",
+        "\n\r\n",
+        "event: ping\n\n",
+        "data: [1,\ndata: 2]\n",
+        "\n",
+        "data: \"x\"",
+    ]);
+    assert_eq!(
+        items(EventStream::events(body)).await,
+        [json!({ "a": 1 }), json!([1, 2]), json!("x")]
+    );
+}
+
+#[tokio::test]
+async fn test_lines_framing() {
+    let body = chunked(&["{\"a\":", "1}\r", "\n2", "\n\n", "\"x\""]);
+    assert_eq!(
+        items(EventStream::lines(body)).await,
+        [json!({ "a": 1 }), json!(2), json!("x")]
+    );
+}
+
+#[tokio::test]
+async fn test_records_framing() {
+    let body = chunked(&["\x1e{\"a\"", ":1}\n", "\x1e", "2\n\x1e\"x", "\""]);
+    assert_eq!(
+        items(EventStream::records(body)).await,
+        [json!({ "a": 1 }), json!(2), json!("x")]
+    );
+}
+
+#[tokio::test]
+async fn test_head_typed_headers() {
+    let (mock, api) = start().await;
+    let response = api
+        .check_environment("environment".to_string())
+        .await
+        .unwrap();
+    assert_eq!(response.status, hyper::StatusCode::OK);
+    assert_eq!(response.typed_headers.x_environment_state, "string");
+    mock.stub("checkEnvironment", |_| {
+        mock::MockResponse::json(hyper::StatusCode::NOT_FOUND, &json!({ "title": "missing" }))
+    });
+    let error = api
+        .check_environment("environment".to_string())
+        .await
+        .unwrap_err();
+    assert!(matches!(error, Error::Status { .. }));
+    assert_eq!(error.status(), Some(hyper::StatusCode::NOT_FOUND));
+    assert_eq!(mock.requests()[1].method, hyper::Method::HEAD);
+}
+
+#[tokio::test]
+async fn test_optional_security() {
+    let mock = mock::MockServer::start().await.unwrap();
+    let api = Api::builder(Credentials::default().has_scheme("secret"))
+        .endpoint(mock.url())
+        .build();
+    let credentials: CredentialsModel = api
+        .get_credentials("environment".to_string())
+        .await
+        .unwrap();
+    assert_eq!(credentials.token.as_deref(), Some("string"));
+    assert_eq!(mock.requests()[0].headers["x-has"], "secret");
+
+    let api = Api::builder(Credentials::default())
+        .endpoint(mock.url())
+        .build();
+    api.get_credentials("environment".to_string())
+        .await
+        .unwrap();
+    assert!(!mock.requests()[1].headers.contains_key("x-has"));
+}
+
+struct Rotating {
+    refreshes: Arc<AtomicUsize>,
+}
+
+impl CredentialProvider for Rotating {
+    fn credentials(&self) -> BoxFuture<'_, Result<Credentials, Error>> {
+        async { Ok(Credentials::default().bearer_auth("old")) }.boxed()
+    }
+
+    fn refresh(&self) -> BoxFuture<'_, Result<Credentials, Error>> {
+        self.refreshes.fetch_add(1, Ordering::SeqCst);
+        async { Ok(Credentials::default().bearer_auth("new")) }.boxed()
+    }
+}
+
+#[tokio::test]
+async fn test_refresh_on_unauthorized() {
+    let mock = mock::MockServer::start().await.unwrap();
+    let refreshes = Arc::new(AtomicUsize::new(0));
+    let api = Api::builder(Rotating {
+        refreshes: refreshes.clone(),
+    })
+    .endpoint(mock.url())
+    .build();
+    mock.stub("checkEnvironment", |request| {
+        match request.headers.get(hyper::header::AUTHORIZATION) {
+            Some(value) if value == "Bearer new" => mock::MockResponse::new(hyper::StatusCode::OK)
+                .header(
+                    hyper::header::HeaderName::from_static("x-environment-state"),
+                    hyper::header::HeaderValue::from_static("live"),
+                ),
+            _ => mock::MockResponse::new(hyper::StatusCode::UNAUTHORIZED),
+        }
+    });
+    let response = api
+        .check_environment("environment".to_string())
+        .await
+        .unwrap();
+    assert_eq!(response.typed_headers.x_environment_state, "live");
+    assert_eq!(refreshes.load(Ordering::SeqCst), 1);
+    let requests = mock.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(
+        requests[0].headers[hyper::header::AUTHORIZATION],
+        "Bearer old"
+    );
+    assert_eq!(
+        requests[1].headers[hyper::header::AUTHORIZATION],
+        "Bearer new"
+    );
+
+    mock.stub("checkEnvironment", |_| {
+        mock::MockResponse::new(hyper::StatusCode::UNAUTHORIZED)
+    });
+    let error = api
+        .check_environment("environment".to_string())
+        .await
+        .unwrap_err();
+    assert_eq!(error.status(), Some(hyper::StatusCode::UNAUTHORIZED));
+    assert_eq!(refreshes.load(Ordering::SeqCst), 2);
+    assert_eq!(mock.requests().len(), 4);
+}
+
+#[test]
+fn test_server_variables() {
+    assert_eq!(Server::default().url(), "http://localhost:8080");
+    assert_eq!(Server::regional().url(), "https://us.example.com/v1");
+    let server = Server::Regional {
+        region: RegionalRegion::Eu,
+        base_path: "v2".to_string(),
+    };
+    assert_eq!(server.url(), "https://eu.example.com/v2");
+}
+
+#[test]
+fn test_blocking() {
+    let (sender, receiver) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let mock = mock::MockServer::start().await.unwrap();
+            sender.send(mock.url()).unwrap();
+            std::future::pending::<()>().await;
+        });
+    });
+    let api = blocking::Api::from(
+        Api::builder(Credentials::default().bearer_auth("key"))
+            .endpoint(receiver.recv().unwrap())
+            .build(),
+    );
+    let response = api.check_environment("environment".to_string()).unwrap();
+    assert_eq!(response.typed_headers.x_environment_state, "string");
+    let settings = api
+        .update_settings_with_response(
+            "environment".to_string(),
+            Settings {
+                name: "web".to_string(),
+                replicas: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(settings.status, hyper::StatusCode::NO_CONTENT);
+}
+
+#[derive(Clone, Default)]
+struct Recorder {
+    requests: Arc<Mutex<Vec<(hyper::Method, String)>>>,
+}
+
+impl HttpTransport for Recorder {
+    fn send(
+        &self,
+        request: hyper::Request<hyper::Body>,
+    ) -> BoxFuture<'static, Result<hyper::Response<hyper::Body>, Error>> {
+        let mut requests = self.requests.lock().unwrap();
+        requests.push((request.method().clone(), request.uri().to_string()));
+        let response = match requests.len() {
+            1 => Err(Error::Connection("connection refused".into())),
+            _ => Ok(hyper::Response::builder()
+                .header("x-environment-state", "memory")
+                .body(hyper::Body::empty())
+                .unwrap()),
+        };
+        async move { response }.boxed()
+    }
+}
+
+#[tokio::test]
+async fn test_custom_transport() {
+    let transport = Recorder::default();
+    let api = Api::builder(Credentials::default().bearer_auth("key"))
+        .endpoint("http://example.invalid")
+        .transport(transport.clone())
+        .retry(RetryPolicy {
+            initial_backoff: Duration::from_millis(1),
+            ..Default::default()
+        })
+        .build();
+    let response = api
+        .check_environment("environment".to_string())
+        .await
+        .unwrap();
+    assert_eq!(response.typed_headers.x_environment_state, "memory");
+    let uri = "http://example.invalid/v1/environments/environment".to_string();
+    assert_eq!(
+        *transport.requests.lock().unwrap(),
+        vec![
+            (hyper::Method::HEAD, uri.clone()),
+            (hyper::Method::HEAD, uri)
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_typed_headers() {
+    let (mock, api) = start().await;
+    let id = || "environment".to_string();
+    let response = api.get_environment_state_with_response(id()).await.unwrap();
+    assert_eq!(response.status, hyper::StatusCode::OK);
+    assert_eq!(response.typed_headers.x_request_id, "string");
+    assert_eq!(response.typed_headers.x_rate_limit_remaining, Some(0));
+    mock.stub("getEnvironmentState", |_| {
+        mock::MockResponse::json(hyper::StatusCode::OK, &json!({ "name": "web" }))
+    });
+    api.get_environment_state(id()).await.unwrap();
+    let error = api
+        .get_environment_state_with_response(id())
+        .await
+        .unwrap_err();
+    assert!(matches!(error, Error::Validation(_)));
+    mock.stub("getEnvironmentState", |_| {
+        mock::MockResponse::json(hyper::StatusCode::NOT_FOUND, &json!({ "title": "missing" }))
+    });
+    let error = api
+        .get_environment_state_with_response(id())
+        .await
+        .unwrap_err();
+    assert!(!matches!(error, Error::Validation(_)));
+    assert_eq!(error.status(), Some(hyper::StatusCode::NOT_FOUND));
+}
+
+struct Environments;
+
+impl service::ApiService for Environments {
+    fn get_environment_state<'a>(
+        &'a self,
+        environment_id: String,
+    ) -> BoxFuture<
+        'a,
+        Result<ApiResponse<Settings, GetEnvironmentStateHeaders>, service::ServiceError>,
+    > {
+        async move {
+            Ok(ApiResponse::new(
+                Settings {
+                    name: "web".to_string(),
+                    replicas: None,
+                },
+                GetEnvironmentStateHeaders {
+                    x_request_id: environment_id,
+                    x_rate_limit_remaining: Some(7),
+                },
+            ))
+        }
+        .boxed()
+    }
+}
+
+#[tokio::test]
+async fn test_service_headers() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let server = tokio::spawn(service::Router::new(Environments).serve(listener));
+    let api = Api::builder(Credentials::default().bearer_auth("key"))
+        .endpoint(format!("http://{address}"))
+        .build();
+    let response = api
+        .get_environment_state_with_response("my environment/1".to_string())
+        .await
+        .unwrap();
+    assert_eq!(response.body.name, "web");
+    assert_eq!(response.typed_headers.x_request_id, "my environment/1");
+    assert_eq!(response.typed_headers.x_rate_limit_remaining, Some(7));
+    server.abort();
+}